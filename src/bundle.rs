@@ -0,0 +1,217 @@
+use kuchiki::NodeRef;
+use percent_encoding::percent_decode_str;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    article_files, helper, is_hidden,
+    transform::{Transform, TransformContext},
+    Article,
+};
+
+// 文章包是一个目录，其中的 index.md 是文章，其余文件与文章放在一起，在文章地址之下提供：
+//
+// ```text
+// articles/trip/
+//   index.md        /articles/trip
+//   map.png         /articles/trip/map.png
+//   data/gps.csv    /articles/trip/data/gps.csv
+// ```
+const INDEX_FILE: &str = "index.md";
+
+// 正文中可能引用文件的属性
+const LINK_ATTRIBUTES: [&str; 3] = ["src", "href", "poster"];
+
+// 渲染文章包时用于改写相对地址
+pub struct Bundle<'a> {
+    // 文章的 id，设置了 slug 时为替换后的 id
+    pub id: &'a str,
+    pub dir: &'a Path,
+}
+
+// 文件是文章包的 index.md 时返回所在目录。直接放在文章目录下的 index.md 是普通文章
+pub fn bundle_dir(articles_dir: &Path, path: &Path) -> Option<PathBuf> {
+    if path.file_name()? != INDEX_FILE {
+        return None;
+    }
+    let dir = path.parent()?;
+    (dir != articles_dir).then(|| dir.to_path_buf())
+}
+
+// 文章包中的文件所属的 index.md，取最近的一层，用于文件变化时重新渲染文章
+pub fn index_file(articles_dir: &Path, path: &Path) -> Option<PathBuf> {
+    if is_hidden(path) {
+        return None;
+    }
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(articles_dir) && *dir != articles_dir)
+        .map(|dir| dir.join(INDEX_FILE))
+        .find(|index| index.is_file())
+}
+
+// 把文章包正文中的相对地址改为文章包下的绝对地址。文章页面的地址不以 `/` 结尾，
+// 不改写的话 `map.png` 会被浏览器解析为 `/articles/map.png`
+pub struct BundleLinks;
+
+impl Transform for BundleLinks {
+    fn name(&self) -> &'static str {
+        "bundle_links"
+    }
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+        if let Some(bundle) = context.bundle {
+            let warnings = rewrite_links(document, bundle, &mut context.dependencies);
+            context.warnings.extend(warnings);
+        }
+        document.clone()
+    }
+}
+
+// 返回引用了不存在的文件时的警告，检查过的文件记录在 `dependencies` 中
+fn rewrite_links(
+    document: &NodeRef,
+    bundle: &Bundle,
+    dependencies: &mut Vec<PathBuf>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for element in document.select("[src], [href], [poster]").unwrap() {
+        let mut attributes = element.attributes.borrow_mut();
+        for name in LINK_ATTRIBUTES {
+            let Some(value) = attributes.get(name).map(str::to_string) else {
+                continue;
+            };
+            let Some((path, suffix)) = relative_reference(&value) else {
+                continue;
+            };
+
+            let decoded = percent_decode_str(path).decode_utf8_lossy();
+            if !exists(&bundle.dir.join(decoded.as_ref()), dependencies) {
+                warnings.push(format!(
+                    "`{}` does not exist in {}",
+                    value,
+                    bundle.dir.display()
+                ));
+            }
+
+            let url = format!("{}{}", resolve(bundle.id, &decoded), suffix);
+            attributes.insert(name, url);
+        }
+    }
+
+    warnings
+}
+
+// 相对地址拆分为路径与 `?`、`#` 之后的部分；绝对地址、带协议的地址与页内锚点返回 None
+fn relative_reference(value: &str) -> Option<(&str, &str)> {
+    let value = value.trim();
+    let first_segment = value.split(['/', '?', '#']).next().unwrap_or("");
+    if value.is_empty() || value.starts_with(['/', '?', '#']) || first_segment.contains(':') {
+        return None;
+    }
+
+    let end = value.find(['?', '#']).unwrap_or(value.len());
+    Some(value.split_at(end))
+}
+
+// 以文章包目录为基准解析路径，`..` 可以指向包外的文章，结尾的 `/` 保留
+fn resolve(id: &str, path: &str) -> String {
+    let mut segments: Vec<&str> = id.split('/').collect();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let url = helper::article_path(&segments.join("/"));
+    if path.ends_with('/') && !url.ends_with('/') {
+        format!("{}/", url)
+    } else {
+        url
+    }
+}
+
+// 文件或目录存在，或者是省略了 `.md` 的文章地址
+fn exists(path: &Path, dependencies: &mut Vec<PathBuf>) -> bool {
+    let mut markdown = OsString::from(path.as_os_str());
+    markdown.push(".md");
+    let markdown = PathBuf::from(markdown);
+    let exists = path.exists() || markdown.is_file();
+    dependencies.push(path.to_path_buf());
+    dependencies.push(markdown);
+    exists
+}
+
+// `/articles/` 之后的路径指向某个文章包中的文件时返回所属的文章与该文件，按最近的一层文章包匹配。
+// 文章本身（Markdown 文件）与隐藏文件不对外提供
+pub fn find_asset<'a>(
+    store: &'a HashMap<String, Article>,
+    path: &str,
+) -> Option<(&'a Article, PathBuf)> {
+    let mut prefix = path;
+    while let Some((parent, _)) = prefix.rsplit_once('/') {
+        prefix = parent;
+        let Some((article, dir)) = store
+            .get(prefix)
+            .and_then(|article| Some((article, article.bundle_dir.as_ref()?)))
+        else {
+            continue;
+        };
+
+        let rest = &path[prefix.len() + 1..];
+        let file = dir.join(rest);
+        let allowed = rest
+            .split('/')
+            .all(|segment| !segment.is_empty() && !segment.starts_with('.'))
+            && file.extension().and_then(|s| s.to_str()) != Some("md");
+        return (allowed && file.is_file()).then_some((article, file));
+    }
+    None
+}
+
+// 文章包中除 Markdown 文件以外的文件，返回相对包目录的路径
+pub fn assets(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    Ok(article_files(dir)?
+        .into_iter()
+        .filter(|path| path.extension().and_then(|s| s.to_str()) != Some("md"))
+        .filter_map(|path| path.strip_prefix(dir).ok().map(Path::to_path_buf))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_reference_splits_off_query_and_fragment() {
+        assert_eq!(relative_reference("map.png"), Some(("map.png", "")));
+        assert_eq!(relative_reference(" ../other#intro "), Some(("../other", "#intro")));
+        assert_eq!(relative_reference("data.csv?raw=1#L2"), Some(("data.csv", "?raw=1#L2")));
+    }
+
+    #[test]
+    fn absolute_urls_and_anchors_are_not_relative() {
+        assert_eq!(relative_reference("/public/avatar.jpg"), None);
+        assert_eq!(relative_reference("https://example.com/a.png"), None);
+        assert_eq!(relative_reference("mailto:me@example.com"), None);
+        assert_eq!(relative_reference("#footnote-1"), None);
+        assert_eq!(relative_reference("?page=2"), None);
+        assert_eq!(relative_reference(""), None);
+    }
+
+    #[test]
+    fn resolves_against_the_bundle_directory() {
+        assert_eq!(resolve("trip", "map.png"), "/articles/trip/map.png");
+        assert_eq!(resolve("trip", "./photos/day-1.jpg"), "/articles/trip/photos/day-1.jpg");
+        assert_eq!(resolve("notes/trip", "../hello-world"), "/articles/notes/hello-world");
+        assert_eq!(resolve("notes/trip", "photos/"), "/articles/notes/trip/photos/");
+    }
+}
@@ -0,0 +1,385 @@
+use chrono::{FixedOffset, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    helper,
+    transform::{self, TransformSettings},
+};
+
+// 未通过 --config 或 BLOG_CONFIG 指定时读取的配置文件，不存在时全部使用默认值
+const DEFAULT_CONFIG_PATH: &str = "blog.toml";
+
+// 站点配置，优先级从低到高依次为：默认值、blog.toml、环境变量、命令行参数
+//
+// ```toml
+// title = "Camille's Blog"
+// base_url = "https://example.com"
+// timezone = "+08:00"
+//
+// [features]
+// search = false
+//
+// [transforms]
+// disable = ["mermaid"]
+// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind: String,
+    // 未指定时，生产环境使用 80，开发环境使用 3000
+    pub port: Option<u16>,
+    // 文章所在目录
+    pub content_dir: PathBuf,
+    // 主题所在目录与使用的主题
+    pub theme_dir: PathBuf,
+    pub theme: String,
+    // 站点自己的模板与静态资源，覆盖主题中的同名文件；静态资源以 /public 对外提供
+    pub template_dir: PathBuf,
+    pub static_dir: PathBuf,
+    // 跟随系统的浅色/深色设置，或固定使用其中一种
+    pub color_scheme: ColorScheme,
+    pub title: String,
+    // 列表页等没有摘要的页面使用的 meta description
+    pub description: Option<String>,
+    pub author: Option<String>,
+    // 站点的绝对地址，用于订阅源；未指定时不提供订阅源
+    #[serde(deserialize_with = "deserialize_base_url")]
+    pub base_url: Option<String>,
+    // 页面的 lang 属性与订阅源的语言
+    pub language: String,
+    // 显示与解析日期时使用的时区
+    pub timezone: Timezone,
+    // 列表页每页的文章数
+    pub page_size: usize,
+    // 文章改名后自动记录的重定向
+    pub redirects_file: PathBuf,
+    // 生产环境中预览草稿与定时发布的文章：/articles/foo?preview=<preview_token>，未设置时不能预览
+    pub preview_token: Option<String>,
+    // mermaid-cli（mmdc）的路径，设置后在服务端把 Mermaid 图表渲染为 SVG
+    pub mermaid_cli: Option<String>,
+    // 代码高亮主题，设置后浅色与深色配色都使用它，否则使用主题 theme.toml 中的设置
+    pub highlight_theme: Option<String>,
+    pub features: Features,
+    // HTML 变换的顺序、禁用的变换与各自的配置，文章的头部元数据可以覆盖
+    #[serde(deserialize_with = "transform::deserialize_settings")]
+    pub transforms: TransformSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: "0.0.0.0".to_string(),
+            port: None,
+            content_dir: PathBuf::from("articles"),
+            theme_dir: PathBuf::from("themes"),
+            theme: "default".to_string(),
+            template_dir: PathBuf::from("templates"),
+            static_dir: PathBuf::from("src/public"),
+            color_scheme: ColorScheme::Auto,
+            title: "Camille's Blog".to_string(),
+            description: None,
+            author: None,
+            base_url: None,
+            language: "zh".to_string(),
+            timezone: Timezone::Local,
+            page_size: 10,
+            redirects_file: PathBuf::from("redirects.toml"),
+            preview_token: None,
+            mermaid_cli: None,
+            highlight_theme: None,
+            features: Features::default(),
+            transforms: TransformSettings::default(),
+        }
+    }
+}
+
+// 可以单独关闭的功能
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    // /search 与 /api/search
+    pub search: bool,
+    // RSS 与 Atom 订阅源
+    pub feeds: bool,
+    // 标签与分类页面
+    pub taxonomies: bool,
+    // 开发模式下保存文章后自动刷新页面
+    pub live_reload: bool,
+    // 为本地图片生成不同宽度与格式的版本
+    pub responsive_images: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features {
+            search: true,
+            feeds: true,
+            taxonomies: true,
+            live_reload: true,
+            responsive_images: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    Auto,
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "auto" => Some(ColorScheme::Auto),
+            "light" => Some(ColorScheme::Light),
+            "dark" => Some(ColorScheme::Dark),
+            _ => None,
+        }
+    }
+}
+
+// `local` 表示服务器所在时区，也可以写 `UTC` 或 `+08:00` 这样的固定偏移
+#[derive(Debug, Clone, Copy)]
+pub enum Timezone {
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "local" | "Local" => Some(Timezone::Local),
+            "UTC" | "utc" | "Z" => Some(Timezone::Fixed(FixedOffset::east_opt(0)?)),
+            offset => offset.parse().ok().map(Timezone::Fixed),
+        }
+    }
+
+    // 把不带时区的日期时间解释为该时区的时间
+    pub fn resolve_local(self, naive: &NaiveDateTime) -> Option<SystemTime> {
+        match self {
+            Timezone::Local => Local.from_local_datetime(naive).earliest().map(SystemTime::from),
+            Timezone::Fixed(offset) => offset
+                .from_local_datetime(naive)
+                .earliest()
+                .map(SystemTime::from),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Timezone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Timezone::parse(&value).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "invalid timezone `{}`, expected `local`, `UTC` or an offset like `+08:00`",
+                value
+            ))
+        })
+    }
+}
+
+fn deserialize_base_url<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.map(|url| normalize_base_url(&url)))
+}
+
+fn normalize_base_url(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+impl Config {
+    // 读取配置，返回配置与未识别的命令行参数（交给子命令处理）
+    pub fn load(args: &[String]) -> anyhow::Result<(Config, Vec<String>)> {
+        let (path, overrides, rest) = parse_args(args)?;
+
+        let path = path.or_else(|| env::var("BLOG_CONFIG").ok().map(PathBuf::from));
+        let mut config = match &path {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).is_file() => {
+                Config::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Config::default(),
+        };
+
+        config.apply_env()?;
+        for (flag, value) in overrides {
+            config
+                .set(flag, &value)
+                .map_err(|e| anyhow::anyhow!("--{}: {}", flag, e))?;
+        }
+
+        if config.page_size == 0 {
+            anyhow::bail!("page_size must be greater than 0");
+        }
+
+        // 订阅源中的链接必须是绝对地址，不能根据请求的 Host 猜测
+        if config.features.feeds && config.base_url.is_none() {
+            eprintln!("No base URL given (base_url, --base-url or BLOG_BASE_URL), feeds are disabled");
+            config.features.feeds = false;
+        }
+
+        Ok((config, rest))
+    }
+
+    fn from_file(path: &Path) -> anyhow::Result<Config> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path.display(), e))?;
+        toml::from_str(&source).map_err(|e| anyhow::anyhow!("Error parsing {}: {}", path.display(), e))
+    }
+
+    fn apply_env(&mut self) -> anyhow::Result<()> {
+        for (variable, key) in [
+            ("BLOG_BIND", "bind"),
+            ("BLOG_PORT", "port"),
+            ("BLOG_CONTENT_DIR", "content-dir"),
+            ("BLOG_THEME_DIR", "theme-dir"),
+            ("BLOG_THEME", "theme"),
+            ("BLOG_TEMPLATE_DIR", "template-dir"),
+            ("BLOG_STATIC_DIR", "static-dir"),
+            ("BLOG_COLOR_SCHEME", "color-scheme"),
+            ("BLOG_TITLE", "title"),
+            ("BLOG_DESCRIPTION", "description"),
+            ("BLOG_AUTHOR", "author"),
+            ("BLOG_BASE_URL", "base-url"),
+            ("BLOG_LANGUAGE", "language"),
+            ("BLOG_TIMEZONE", "timezone"),
+            ("BLOG_PAGE_SIZE", "page-size"),
+            ("BLOG_REDIRECTS_FILE", "redirects-file"),
+            ("BLOG_PREVIEW_TOKEN", "preview-token"),
+            ("BLOG_MERMAID_CLI", "mermaid-cli"),
+            ("BLOG_HIGHLIGHT_THEME", "highlight-theme"),
+            ("BLOG_FEATURES_SEARCH", "features.search"),
+            ("BLOG_FEATURES_FEEDS", "features.feeds"),
+            ("BLOG_FEATURES_TAXONOMIES", "features.taxonomies"),
+            ("BLOG_FEATURES_LIVE_RELOAD", "features.live-reload"),
+            ("BLOG_FEATURES_RESPONSIVE_IMAGES", "features.responsive-images"),
+        ] {
+            if let Ok(value) = env::var(variable) {
+                self.set(key, &value)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", variable, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "bind" => self.bind = value.to_string(),
+            "port" => self.port = Some(value.parse()?),
+            "content-dir" => self.content_dir = PathBuf::from(value),
+            "theme-dir" => self.theme_dir = PathBuf::from(value),
+            "theme" => self.theme = value.to_string(),
+            "template-dir" => self.template_dir = PathBuf::from(value),
+            "static-dir" => self.static_dir = PathBuf::from(value),
+            "color-scheme" => {
+                self.color_scheme = ColorScheme::parse(value).ok_or_else(|| {
+                    anyhow::anyhow!("invalid color scheme `{}`, expected auto, light or dark", value)
+                })?
+            }
+            "title" => self.title = value.to_string(),
+            "description" => self.description = Some(value.to_string()),
+            "author" => self.author = Some(value.to_string()),
+            "base-url" => self.base_url = Some(normalize_base_url(value)),
+            "language" => self.language = value.to_string(),
+            "timezone" => {
+                self.timezone = Timezone::parse(value)
+                    .ok_or_else(|| anyhow::anyhow!("invalid timezone `{}`", value))?
+            }
+            "page-size" => self.page_size = value.parse()?,
+            "redirects-file" => self.redirects_file = PathBuf::from(value),
+            "preview-token" => self.preview_token = Some(value.to_string()),
+            "mermaid-cli" => self.mermaid_cli = Some(value.to_string()),
+            "highlight-theme" => self.highlight_theme = Some(value.to_string()),
+            "features.search" => self.features.search = parse_bool(value)?,
+            "features.feeds" => self.features.feeds = parse_bool(value)?,
+            "features.taxonomies" => self.features.taxonomies = parse_bool(value)?,
+            "features.live-reload" => self.features.live_reload = parse_bool(value)?,
+            "features.responsive-images" => self.features.responsive_images = parse_bool(value)?,
+            _ => anyhow::bail!("unknown setting: {}", key),
+        }
+        Ok(())
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+            .unwrap_or(if helper::is_development() { 3000 } else { 80 })
+    }
+}
+
+// 可以在命令行中覆盖的设置，形如 `--port 8080`、`--features.search false`
+const CLI_SETTINGS: [&str; 24] = [
+    "bind",
+    "port",
+    "content-dir",
+    "theme-dir",
+    "theme",
+    "template-dir",
+    "static-dir",
+    "color-scheme",
+    "title",
+    "description",
+    "author",
+    "base-url",
+    "language",
+    "timezone",
+    "page-size",
+    "redirects-file",
+    "preview-token",
+    "mermaid-cli",
+    "highlight-theme",
+    "features.search",
+    "features.feeds",
+    "features.taxonomies",
+    "features.live-reload",
+    "features.responsive-images",
+];
+
+fn parse_bool(value: &str) -> anyhow::Result<bool> {
+    match value.trim() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => anyhow::bail!("invalid value `{}`, expected true or false", value),
+    }
+}
+
+type ParsedArgs = (Option<PathBuf>, Vec<(&'static str, String)>, Vec<String>);
+
+fn parse_args(args: &[String]) -> anyhow::Result<ParsedArgs> {
+    let mut path = None;
+    let mut overrides = Vec::new();
+    let mut rest = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            rest.push(arg.clone());
+            continue;
+        };
+
+        if flag == "config" {
+            path = Some(
+                args.next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| anyhow::anyhow!("--config requires a file"))?,
+            );
+        } else if let Some(&key) = CLI_SETTINGS.iter().find(|&&key| key == flag) {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("--{} requires a value", key))?;
+            overrides.push((key, value.clone()));
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+
+    Ok((path, overrides, rest))
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use minijinja::context;
+
+use crate::{
+    config::Config,
+    helper,
+    pagination::{page_path, paginate, render_pagination},
+    sorted_articles,
+    template::{ArticleContext, Breadcrumb, Templates},
+    Article,
+};
+
+// 子目录的地址以 `/` 结尾，例如 `/articles/notes/`，与同名文章区分
+pub fn directory_path(dir: &str) -> String {
+    format!("{}/", helper::article_path(dir))
+}
+
+// 文章 id 所在的目录，顶层文章返回 None
+pub fn parent(id: &str) -> Option<&str> {
+    id.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+// 形如 `notes/page/2` 的目录分页地址，拆分为目录与页码
+pub fn split_page(path: &str) -> Option<(&str, usize)> {
+    let (dir, page) = path.rsplit_once("/page/")?;
+    Some((dir, page.parse().ok()?))
+}
+
+// 所有包含可见文章的目录，包括只含子目录的上级目录
+pub fn directories(store: &HashMap<String, Article>) -> BTreeSet<&str> {
+    let mut directories = BTreeSet::new();
+    for (id, _) in sorted_articles(store) {
+        let mut current = parent(id);
+        while let Some(dir) = current {
+            directories.insert(dir);
+            current = parent(dir);
+        }
+    }
+    directories
+}
+
+// 子目录及其中（含更深层）的文章数量
+#[derive(Serialize)]
+struct Subdirectory<'a> {
+    name: &'a str,
+    url: String,
+    count: usize,
+}
+
+// 列出目录下的子目录与文章，目录中没有文章或页码越界时返回 None
+pub fn render_directory(
+    store: &HashMap<String, Article>,
+    dir: &str,
+    page: usize,
+    config: &Config,
+    templates: &Templates,
+) -> Option<String> {
+    let prefix = format!("{}/", dir);
+    let mut subdirectories: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut articles: Vec<(&String, &Article)> = Vec::new();
+
+    for (id, article) in sorted_articles(store) {
+        let Some(rest) = id.strip_prefix(&prefix) else {
+            continue;
+        };
+        match rest.split_once('/') {
+            Some((name, _)) => *subdirectories.entry(name).or_insert(0) += 1,
+            None => articles.push((id, article)),
+        }
+    }
+
+    if subdirectories.is_empty() && articles.is_empty() {
+        return None;
+    }
+
+    let page = paginate(articles, page, config.page_size)?;
+
+    let subdirectories: Vec<Subdirectory> = subdirectories
+        .into_iter()
+        .map(|(name, count)| Subdirectory {
+            name,
+            url: directory_path(&format!("{}{}", prefix, name)),
+            count,
+        })
+        .collect();
+    let articles: Vec<ArticleContext> = page
+        .items
+        .iter()
+        .map(|(id, article)| ArticleContext::new(id, article, config))
+        .collect();
+
+    let base_path = helper::article_path(dir);
+    Some(templates.render(
+        "directory.html",
+        context! {
+            section => "articles",
+            directory => context! {
+                name => dir.rsplit('/').next().unwrap_or(dir),
+                path => dir,
+                url => directory_path(dir),
+                breadcrumbs => Breadcrumb::ancestors(parent(dir)),
+            },
+            subdirectories,
+            articles,
+            page => context! { current => page.current, total_pages => page.total_pages },
+            // 只有文章分页，子目录在每一页都显示
+            pagination => render_pagination(&page, |n| {
+                if n <= 1 {
+                    directory_path(dir)
+                } else {
+                    page_path(&base_path, n)
+                }
+            }),
+        },
+    ))
+}
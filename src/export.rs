@@ -0,0 +1,213 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    bundle,
+    config::Config,
+    directory,
+    feed::{self, FeedFormat},
+    helper, highlight,
+    init_article_store,
+    pagination::page_path,
+    redirect::{self, Redirects},
+    responsive_image,
+    render_article, render_index,
+    taxonomy::{self, Taxonomy},
+    template::Templates,
+    theme::Theme,
+};
+
+// 解析 `blog build` 的参数并导出静态站点，站点地址等设置由 `Config` 处理
+pub async fn run(
+    args: &[String],
+    config: &Arc<Config>,
+    theme: &Arc<Theme>,
+    templates: &Templates,
+) -> anyhow::Result<()> {
+    let mut out = PathBuf::from("dist");
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => {
+                out = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| anyhow::anyhow!("--out requires a directory"))?;
+            }
+            _ => anyhow::bail!("unknown argument: {}", arg),
+        }
+    }
+
+    build_site(&out, config, theme, templates).await?;
+    println!("Site exported to {}", out.display());
+
+    Ok(())
+}
+
+// 使用与服务器相同的渲染流程，把所有页面写入 `out`
+async fn build_site(
+    out: &Path,
+    config: &Arc<Config>,
+    theme: &Arc<Theme>,
+    templates: &Templates,
+) -> anyhow::Result<()> {
+    let article_store = init_article_store(config, theme).await?;
+    let mut store = article_store.write().await;
+    // 静态站点不包含草稿；定时发布的文章要等到发布时间之后重新导出才会出现
+    store.retain(|_, article| article.is_published());
+
+    tokio::fs::create_dir_all(out).await?;
+
+    // 首页、404 页面与静态资源。先复制上级主题，再由主题与站点的文件覆盖
+    if let Some(index) = theme.find_static("index.html") {
+        let source = tokio::fs::read_to_string(index).await?;
+        tokio::fs::write(out.join("index.html"), templates.render_home(&source)).await?;
+    }
+    tokio::fs::write(out.join("404.html"), templates.render_not_found()).await?;
+    for dir in theme.static_dirs.iter().rev().filter(|dir| dir.is_dir()) {
+        copy_dir(dir, &out.join("public"))?;
+    }
+    tokio::fs::create_dir_all(out.join("public")).await?;
+    for (name, highlight) in [
+        ("highlight-light.css", &theme.highlight.light),
+        ("highlight-dark.css", &theme.highlight.dark),
+    ] {
+        tokio::fs::write(out.join("public").join(name), highlight::theme_css(highlight)).await?;
+    }
+
+    // 目录页。与文章同名的目录会被文章覆盖，与服务器上 `/articles/notes` 的行为一致
+    for dir in directory::directories(&store) {
+        let base_path = format!("/articles/{}", dir);
+        let mut page = 1;
+        while let Some(html) = directory::render_directory(&store, dir, page, config, templates) {
+            write_page(out, &page_path(&base_path, page), &html).await?;
+            page += 1;
+        }
+    }
+
+    // 别名与改名前的旧地址
+    let redirects = Redirects::load(&config.redirects_file)?;
+    let aliases = store
+        .iter()
+        .flat_map(|(id, article)| article.aliases.iter().map(move |alias| (alias.as_str(), id)));
+    for (from, id) in aliases {
+        write_page(out, from, &redirect::redirect_page(&helper::article_path(id))).await?;
+    }
+    for (from, to) in redirects.iter() {
+        write_page(out, from, &redirect::redirect_page(&helper::encode_path(to))).await?;
+    }
+
+    // 文章，id 中的 `/` 对应子目录。文章包中的其他文件放在文章页面旁边
+    for (id, article) in store.iter() {
+        let html = render_article(id, article, config, templates);
+        write_page(out, &format!("/articles/{}", id), &html).await?;
+
+        if let Some(dir) = &article.bundle_dir {
+            let target = out.join("articles").join(id);
+            for asset in bundle::assets(dir)? {
+                let to = target.join(&asset);
+                if let Some(parent) = to.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::copy(dir.join(&asset), to).await?;
+            }
+        }
+
+        // 只复制文章用到的图片版本，缓存中的旧文件不会进入导出结果
+        if !article.images.is_empty() {
+            tokio::fs::create_dir_all(out.join("images")).await?;
+        }
+        for name in &article.images {
+            let from = Path::new(responsive_image::CACHE_DIR).join(name);
+            tokio::fs::copy(from, out.join("images").join(name)).await?;
+        }
+    }
+
+    // 文章列表
+    let mut page = 1;
+    while let Some(html) = render_index(&store, page, config, templates) {
+        write_page(out, &page_path("/articles", page), &html).await?;
+        page += 1;
+    }
+
+    // 标签与分类
+    let taxonomies = if config.features.taxonomies {
+        vec![Taxonomy::Tags, Taxonomy::Categories]
+    } else {
+        Vec::new()
+    };
+    for taxonomy in taxonomies {
+        let html = taxonomy::render_term_index(&store, taxonomy, templates);
+        write_page(out, taxonomy.base_path(), &html).await?;
+
+        for term in taxonomy::term_counts(&store, taxonomy).keys() {
+            // 与文章一样使用解码后的名称作为目录，静态服务器会先解码请求的地址再查找文件。
+            // 名称中不含 `/`、`.` 与 `..`，载入文章时已检查
+            let base_path = format!("{}/{}", taxonomy.base_path(), term);
+            let mut page = 1;
+            while let Some(html) =
+                taxonomy::render_term_listing(&store, taxonomy, term, page, config, templates)
+            {
+                write_page(out, &page_path(&base_path, page), &html).await?;
+                page += 1;
+            }
+        }
+    }
+
+    // 订阅源需要绝对地址，未指定站点地址时 Config::load 已关闭订阅源
+    if let Some(base_url) = config.base_url.as_ref().filter(|_| config.features.feeds) {
+        for format in [FeedFormat::Rss, FeedFormat::Atom] {
+            if let Some(xml) = feed::render_feed(&store, format, None, base_url, config) {
+                tokio::fs::write(out.join(format.file_name()), xml).await?;
+            }
+
+            if !config.features.taxonomies {
+                continue;
+            }
+            for tag in taxonomy::term_counts(&store, Taxonomy::Tags).keys() {
+                if let Some(xml) =
+                    feed::render_feed(&store, format, Some(tag), base_url, config)
+                {
+                    let dir = out.join("tags").join(tag);
+                    tokio::fs::create_dir_all(&dir).await?;
+                    tokio::fs::write(dir.join(format.file_name()), xml).await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// 把地址 `/a/b` 写成 `out/a/b/index.html`
+async fn write_page(out: &Path, url_path: &str, html: &str) -> anyhow::Result<()> {
+    // 地址来自文章 id、别名与 redirects_file，不能写到 out 之外或覆盖首页
+    let relative = Path::new(url_path.trim_start_matches('/'));
+    let inside = relative
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)));
+    if !inside || relative.as_os_str().is_empty() {
+        anyhow::bail!("refusing to write {} outside of {}", url_path, out.display());
+    }
+    let dir = out.join(relative);
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(dir.join("index.html"), html).await?;
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
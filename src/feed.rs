@@ -0,0 +1,343 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use kuchiki::traits::*;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    config::Config,
+    helper, sorted_articles,
+    taxonomy::{term_path, Taxonomy},
+    Article, ArticleStore,
+};
+
+// 订阅源中最多包含的文章数
+const FEED_SIZE: usize = 20;
+
+pub async fn rss_handler(
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+) -> impl IntoResponse {
+    feed_response(FeedFormat::Rss, None, state, config).await
+}
+
+pub async fn atom_handler(
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+) -> impl IntoResponse {
+    feed_response(FeedFormat::Atom, None, state, config).await
+}
+
+pub async fn tag_rss_handler(
+    Path(tag): Path<String>,
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+) -> impl IntoResponse {
+    feed_response(FeedFormat::Rss, Some(&tag), state, config).await
+}
+
+pub async fn tag_atom_handler(
+    Path(tag): Path<String>,
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+) -> impl IntoResponse {
+    feed_response(FeedFormat::Atom, Some(&tag), state, config).await
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+impl FeedFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "feed.xml",
+            FeedFormat::Atom => "atom.xml",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+}
+
+// 订阅源中的一篇文章
+struct Entry<'a> {
+    id: &'a str,
+    article: &'a Article,
+}
+
+async fn feed_response(
+    format: FeedFormat,
+    tag: Option<&str>,
+    state: State<ArticleStore>,
+    State(config): State<Arc<Config>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let store = state.read().await;
+
+    // 没有站点地址时不会启用订阅源（见 Config::load），不根据请求的 Host 推断
+    let base_url = config.base_url.as_deref().ok_or(StatusCode::NOT_FOUND)?;
+    let body =
+        render_feed(&store, format, tag, base_url, &config).ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(([(header::CONTENT_TYPE, format.content_type())], body))
+}
+
+// 生成订阅源，`tag` 不为空时只包含带该标签的文章；标签下没有文章时返回 None
+pub fn render_feed(
+    store: &HashMap<String, Article>,
+    format: FeedFormat,
+    tag: Option<&str>,
+    base_url: &str,
+    config: &Config,
+) -> Option<String> {
+    // 与文章列表相同的排序
+    let entries: Vec<Entry> = sorted_articles(store)
+        .into_iter()
+        .filter(|(_, article)| tag.is_none_or(|tag| article.tags.iter().any(|t| t == tag)))
+        .take(FEED_SIZE)
+        .map(|(id, article)| Entry { id, article })
+        .collect();
+
+    if tag.is_some() && entries.is_empty() {
+        return None;
+    }
+
+    let title = match tag {
+        Some(tag) => format!("{} - {}", config.title, tag),
+        None => config.title.clone(),
+    };
+    let self_path = match tag {
+        Some(tag) => format!("{}/{}", term_path(Taxonomy::Tags, tag), format.file_name()),
+        None => format!("/{}", format.file_name()),
+    };
+
+    Some(match format {
+        FeedFormat::Rss => render_rss(&title, base_url, &self_path, &entries, config),
+        FeedFormat::Atom => render_atom(&title, base_url, &self_path, &entries, config),
+    })
+}
+
+fn article_url(base_url: &str, id: &str) -> String {
+    format!("{}{}", base_url, helper::article_path(id))
+}
+
+// 有摘要时使用摘要，否则使用全文
+fn entry_content(article: &Article, base_url: &str) -> String {
+    absolute_urls(
+        article.summary.as_deref().unwrap_or(&article.body_html),
+        base_url,
+    )
+}
+
+// 阅读器无法解析 `/images/…`、`/public/…` 这类站内地址，改为以 base_url 开头的绝对地址
+fn absolute_urls(html: &str, base_url: &str) -> String {
+    let absolute = |url: &str| match url.starts_with('/') && !url.starts_with("//") {
+        true => format!("{}{}", base_url, url),
+        false => url.to_string(),
+    };
+
+    let document = kuchiki::parse_html().one(html);
+    for element in document.select("[src], [href], [poster], [srcset]").unwrap() {
+        let mut attributes = element.attributes.borrow_mut();
+        for name in ["src", "href", "poster"] {
+            if let Some(url) = attributes.get(name).map(absolute) {
+                attributes.insert(name, url);
+            }
+        }
+        // `srcset` 是逗号分隔的「地址 宽度」列表
+        if let Some(srcset) = attributes.get("srcset") {
+            let srcset = srcset
+                .split(',')
+                .map(|candidate| {
+                    let candidate = candidate.trim();
+                    match candidate.split_once(' ') {
+                        Some((url, descriptor)) => format!("{} {}", absolute(url), descriptor),
+                        None => absolute(candidate),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            attributes.insert("srcset", srcset);
+        }
+    }
+
+    // parse_html 会补全 html、body，只取 body 中的内容
+    document
+        .select_first("body")
+        .map(|body| body.as_node().children().map(|child| child.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn render_rss(
+    title: &str,
+    base_url: &str,
+    self_path: &str,
+    entries: &[Entry],
+    config: &Config,
+) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml.push_str(r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>"#);
+    xml.push_str(&format!("<title>{}</title>", helper::escape_html(title)));
+    xml.push_str(&format!("<link>{}/articles</link>", base_url));
+    xml.push_str(&format!(
+        "<description>{}</description>",
+        helper::escape_html(title)
+    ));
+    xml.push_str(&format!(
+        "<language>{}</language>",
+        helper::escape_html(&config.language)
+    ));
+    xml.push_str(&format!(
+        r#"<atom:link href="{}{}" rel="self" type="application/rss+xml"/>"#,
+        base_url, self_path
+    ));
+    if let Some(latest) = entries.first() {
+        let updated: DateTime<Utc> = latest.article.created_at.into();
+        xml.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>",
+            updated.to_rfc2822()
+        ));
+    }
+
+    for entry in entries {
+        let url = article_url(base_url, entry.id);
+        let published: DateTime<Utc> = entry.article.created_at.into();
+
+        xml.push_str("<item>");
+        xml.push_str(&format!(
+            "<title>{}</title>",
+            helper::escape_html(&helper::html_to_text(&entry.article.title))
+        ));
+        xml.push_str(&format!("<link>{}</link>", url));
+        xml.push_str(&format!(r#"<guid isPermaLink="true">{}</guid>"#, url));
+        xml.push_str(&format!("<pubDate>{}</pubDate>", published.to_rfc2822()));
+        for category in entry.article.tags.iter().chain(&entry.article.categories) {
+            xml.push_str(&format!(
+                "<category>{}</category>",
+                helper::escape_html(category)
+            ));
+        }
+        xml.push_str(&format!(
+            "<description>{}</description>",
+            helper::escape_html(&entry_content(entry.article, base_url))
+        ));
+        xml.push_str("</item>");
+    }
+
+    xml.push_str("</channel></rss>");
+    xml
+}
+
+fn render_atom(
+    title: &str,
+    base_url: &str,
+    self_path: &str,
+    entries: &[Entry],
+    config: &Config,
+) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml.push_str(&format!(
+        r#"<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="{}">"#,
+        helper::escape_html(&config.language)
+    ));
+    xml.push_str(&format!("<title>{}</title>", helper::escape_html(title)));
+    xml.push_str(&format!("<id>{}{}</id>", base_url, self_path));
+    xml.push_str(&format!(r#"<link href="{}/articles"/>"#, base_url));
+    xml.push_str(&format!(
+        r#"<link rel="self" href="{}{}"/>"#,
+        base_url, self_path
+    ));
+
+    // Atom 要求 feed 必须有 updated，取最近一次更新的文章
+    let feed_updated = entries
+        .iter()
+        .map(|entry| entry.article.updated.unwrap_or(entry.article.created_at))
+        .max()
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(Utc::now);
+    xml.push_str(&format!("<updated>{}</updated>", feed_updated.to_rfc3339()));
+    xml.push_str(&format!(
+        "<author><name>{}</name></author>",
+        helper::escape_html(config.author.as_deref().unwrap_or(&config.title))
+    ));
+
+    for entry in entries {
+        let url = article_url(base_url, entry.id);
+        let published: DateTime<Utc> = entry.article.created_at.into();
+        let updated: DateTime<Utc> = entry
+            .article
+            .updated
+            .unwrap_or(entry.article.created_at)
+            .into();
+
+        xml.push_str("<entry>");
+        xml.push_str(&format!(
+            "<title>{}</title>",
+            helper::escape_html(&helper::html_to_text(&entry.article.title))
+        ));
+        xml.push_str(&format!("<id>{}</id>", url));
+        xml.push_str(&format!(r#"<link href="{}"/>"#, url));
+        xml.push_str(&format!(
+            "<published>{}</published>",
+            published.to_rfc3339()
+        ));
+        xml.push_str(&format!("<updated>{}</updated>", updated.to_rfc3339()));
+        for category in entry.article.tags.iter().chain(&entry.article.categories) {
+            xml.push_str(&format!(
+                r#"<category term="{}"/>"#,
+                helper::escape_html(category)
+            ));
+        }
+        let element = match entry.article.summary {
+            Some(_) => "summary",
+            None => "content",
+        };
+        xml.push_str(&format!(
+            r#"<{0} type="html">{1}</{0}>"#,
+            element,
+            helper::escape_html(&entry_content(entry.article, base_url))
+        ));
+        xml.push_str("</entry>");
+    }
+
+    xml.push_str("</feed>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_URL: &str = "https://example.com";
+
+    #[test]
+    fn site_paths_become_absolute() {
+        assert_eq!(
+            absolute_urls(r#"<p><a href="/articles/trip">Trip</a><img src="/images/a.webp"></p>"#, BASE_URL),
+            r#"<p><a href="https://example.com/articles/trip">Trip</a><img src="https://example.com/images/a.webp"></p>"#
+        );
+    }
+
+    #[test]
+    fn other_urls_are_unchanged() {
+        let html = r##"<a href="https://rust-lang.org/">Rust</a><a href="//cdn.example.com/x.js">x</a><a href="#fn-1">1</a>"##;
+        assert_eq!(absolute_urls(html, BASE_URL), html);
+    }
+
+    #[test]
+    fn every_srcset_candidate_is_rewritten() {
+        assert_eq!(
+            absolute_urls(r#"<img srcset="/images/a-480.webp 480w,/images/a-960.webp 960w, https://cdn.example.com/a.webp 2x">"#, BASE_URL),
+            r#"<img srcset="https://example.com/images/a-480.webp 480w, https://example.com/images/a-960.webp 960w, https://cdn.example.com/a.webp 2x">"#
+        );
+    }
+}
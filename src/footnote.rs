@@ -1,14 +1,57 @@
-use kuchiki::{NodeRef};
+use kuchiki::{parse_html, traits::*, NodeRef};
 use markup5ever::QualName;
 use markup5ever::ns;
 use markup5ever::namespace_url;
+use serde::Deserialize;
+use std::collections::HashMap;
 
-pub fn process_footnote(_document: &NodeRef) -> NodeRef {
+use crate::transform::{Transform, TransformContext};
 
-    let document = _document.clone();
+// 把脚注整理为正文末尾的有序列表，引用与脚注之间可以互相跳转
+pub struct Footnotes;
+
+impl Transform for Footnotes {
+    fn name(&self) -> &'static str {
+        "footnotes"
+    }
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+        let options: FootnoteOptions = context.options();
+        process_footnote(document, &options, context)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FootnoteOptions {
+    // 鼠标悬停在引用上时显示脚注内容
+    pub popovers: bool,
+    // 宽屏时把脚注显示在正文右侧的页边
+    pub sidenotes: bool,
+}
 
-    let footnote_definitions: Vec<_> = document.select(".footnote-definition")
-                                        .unwrap().collect();
+impl Default for FootnoteOptions {
+    fn default() -> Self {
+        FootnoteOptions {
+            popovers: true,
+            sidenotes: false,
+        }
+    }
+}
+
+// 同一脚注的全部引用
+struct Note {
+    definition: Option<NodeRef>,
+    references: Vec<NodeRef>,
+}
+
+fn process_footnote(
+    _document: &NodeRef,
+    options: &FootnoteOptions,
+    context: &mut TransformContext,
+) -> NodeRef {
+
+    let document = _document.clone();
 
     let container = match document.select(".container")
                             .unwrap().next() {
@@ -16,26 +59,223 @@ pub fn process_footnote(_document: &NodeRef) -> NodeRef {
         None => return document,
     };
 
-    if footnote_definitions.is_empty() {
+    let mut definitions: HashMap<String, NodeRef> = document
+        .select(".footnote-definition")
+        .unwrap()
+        .filter_map(|definition| {
+            let id = definition.attributes.borrow().get("id")?.to_string();
+            Some((id, definition.as_node().clone()))
+        })
+        .collect();
+
+    // 按首次引用的顺序编号，与 pulldown-cmark 的编号方式无关
+    let mut names: Vec<String> = Vec::new();
+    let mut notes: HashMap<String, Note> = HashMap::new();
+    let references: Vec<NodeRef> = document
+        .select("sup.footnote-reference")
+        .unwrap()
+        .map(|node| node.as_node().clone())
+        .collect();
+
+    for reference in references {
+        let Some(name) = reference_name(&reference) else {
+            continue;
+        };
+
+        let note = notes.entry(name.clone()).or_insert_with(|| {
+            names.push(name.clone());
+            Note {
+                definition: definitions.remove(&name),
+                references: Vec::new(),
+            }
+        });
+        note.references.push(reference);
+    }
+
+    for (name, definition) in definitions {
+        context.warn(format!("footnote `{}` is never referenced", name));
+        definition.detach();
+    }
+
+    if names.is_empty() {
         return document;
     }
 
-    let temp_container = NodeRef::new_element(
-                            QualName::new(None, ns!(html),
-                                "div".into()),
-                            None);
-    temp_container.as_element()
-            .unwrap()
-            .attributes
-            .borrow_mut()
-            .insert("style", String::from("margin: 80px"));
+    let list = new_element("ol");
+    let mut number = 0;
+
+    for name in &names {
+        let note = &notes[name];
 
-    for footnote in footnote_definitions {
-        temp_container.append(footnote.as_node().clone());
+        let Some(definition) = &note.definition else {
+            context.warn(format!("footnote `{}` is not defined", name));
+            for reference in &note.references {
+                reference.insert_before(NodeRef::new_text(format!("[^{}]", name)));
+                reference.detach();
+            }
+            continue;
+        };
+        number += 1;
+
+        let item = new_element("li");
+        set_attribute(&item, "id", note_id(name));
+
+        // 脚注内容，不含 pulldown-cmark 生成的编号
+        let children: Vec<NodeRef> = definition.children().collect();
+        for child in children {
+            if is_definition_label(&child) {
+                continue;
+            }
+            item.append(child);
+        }
+        definition.detach();
+
+        let popover = if options.popovers || options.sidenotes {
+            Some(popover_html(&item, number))
+        } else {
+            None
+        };
+
+        for (index, reference) in note.references.iter().enumerate() {
+            let reference_id = reference_id(name, index);
+            reference.insert_before(reference_link(name, &reference_id, number, popover.as_deref()));
+            reference.detach();
+        }
+
+        append_backlinks(&item, name, note.references.len());
+        list.append(item);
+    }
+
+    if number == 0 {
+        return document;
     }
 
-    container.as_node().append(temp_container);
-    
+    // 页面容器由模板生成，侧注的样式通过脚注区域的类名判断
+    let class = if options.sidenotes { "footnotes sidenotes" } else { "footnotes" };
+    let section = new_element("section");
+    set_attribute(&section, "class", class.to_string());
+    set_attribute(&section, "role", "doc-endnotes".to_string());
+    section.append(new_element("hr"));
+    section.append(list);
+    container.as_node().append(section);
+
     document
 
 }
+
+// 引用的链接形如 `#name`，取出脚注名称
+fn reference_name(reference: &NodeRef) -> Option<String> {
+    let link = reference.select_first("a").ok()?;
+    let href = link.attributes.borrow().get("href")?.to_string();
+    href.strip_prefix('#').map(str::to_string)
+}
+
+fn note_id(name: &str) -> String {
+    format!("fn-{}", name)
+}
+
+// 第一处引用为 fnref-name，之后依次为 fnref-name-2、fnref-name-3
+fn reference_id(name: &str, index: usize) -> String {
+    if index == 0 {
+        format!("fnref-{}", name)
+    } else {
+        format!("fnref-{}-{}", name, index + 1)
+    }
+}
+
+fn reference_link(name: &str, reference_id: &str, number: usize, popover: Option<&str>) -> NodeRef {
+    let sup = new_element("sup");
+    set_attribute(&sup, "class", "footnote-reference".to_string());
+    set_attribute(&sup, "id", reference_id.to_string());
+
+    let link = new_element("a");
+    set_attribute(&link, "href", format!("#{}", note_id(name)));
+    set_attribute(&link, "role", "doc-noteref".to_string());
+    link.append(NodeRef::new_text(number.to_string()));
+    sup.append(link);
+
+    if let Some(popover) = popover {
+        let fragment = parse_html().one(popover);
+        if let Ok(span) = fragment.select_first("span.footnote-popover") {
+            sup.append(span.as_node().clone());
+        }
+    }
+
+    sup
+}
+
+// 每处引用对应一个 "↩"，引用多于一处时加上序号
+fn append_backlinks(item: &NodeRef, name: &str, count: usize) {
+    // 放在最后一个段落的末尾，避免单独占一行
+    let last_element = item
+        .children()
+        .rev()
+        .find(|child| child.as_element().is_some() || !child.text_contents().trim().is_empty());
+    let target = match last_element {
+        Some(last) if last.as_element().is_some_and(|e| &*e.name.local == "p") => last,
+        _ => item.clone(),
+    };
+
+    for index in 0..count {
+        target.append(NodeRef::new_text(" "));
+
+        let link = new_element("a");
+        set_attribute(&link, "href", format!("#{}", reference_id(name, index)));
+        set_attribute(&link, "class", "footnote-backref".to_string());
+        set_attribute(&link, "role", "doc-backlink".to_string());
+        link.append(NodeRef::new_text("↩"));
+        if count > 1 {
+            let sup = new_element("sup");
+            sup.append(NodeRef::new_text((index + 1).to_string()));
+            link.append(sup);
+        }
+        target.append(link);
+    }
+}
+
+// 悬停提示位于 sup 内，只能包含行内内容，因此段落之间用换行分隔，其他块级元素只保留文字
+fn popover_html(item: &NodeRef, number: usize) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for child in item.children() {
+        match child.as_element() {
+            Some(element) if &*element.name.local == "p" => {
+                parts.push(child.children().map(|node| node.to_string()).collect());
+            }
+            Some(_) => parts.push(crate::helper::escape_html(child.text_contents().trim())),
+            None => {
+                let text = child.text_contents();
+                if !text.trim().is_empty() {
+                    parts.push(crate::helper::escape_html(text.trim()));
+                }
+            }
+        }
+    }
+
+    format!(
+        r#"<span class="footnote-popover" role="note"><span class="footnote-popover-number">{}.</span> {}</span>"#,
+        number,
+        parts.join("<br>")
+    )
+}
+
+fn is_definition_label(node: &NodeRef) -> bool {
+    node.as_element().is_some_and(|element| {
+        element
+            .attributes
+            .borrow()
+            .get("class")
+            .is_some_and(|class| class.split_whitespace().any(|c| c == "footnote-definition-label"))
+    })
+}
+
+fn set_attribute(node: &NodeRef, name: &str, value: String) {
+    node.as_element()
+        .unwrap()
+        .attributes
+        .borrow_mut()
+        .insert(name, value);
+}
+
+fn new_element(name: &str) -> NodeRef {
+    NodeRef::new_element(QualName::new(None, ns!(html), name.into()), None)
+}
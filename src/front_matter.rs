@@ -0,0 +1,192 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::transform::{self, TransformSettings};
+
+// 文章头部元数据，支持 YAML（---）与 TOML（+++）两种写法
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub updated: Option<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub tags: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub categories: Vec<String>,
+    pub summary: Option<String>,
+    pub draft: bool,
+    pub slug: Option<String>,
+    // 文章以前的地址，访问时重定向到当前地址
+    #[serde(deserialize_with = "one_or_many")]
+    pub aliases: Vec<String>,
+    #[serde(deserialize_with = "transform::deserialize_settings")]
+    pub transforms: TransformSettings,
+    pub toc: Option<serde_yaml::Value>,
+}
+
+impl FrontMatter {
+    // 文章的变换设置，`toc: false` 或 `toc: { max_level: 3 }` 是目录配置的简写
+    pub fn transform_settings(&self) -> TransformSettings {
+        let mut settings = self.transforms.clone();
+        let toc_options = settings
+            .options
+            .entry("table_of_contents".to_string())
+            .or_default();
+
+        match &self.toc {
+            Some(serde_yaml::Value::Bool(enabled)) => {
+                toc_options.insert("enabled".to_string(), serde_yaml::Value::Bool(*enabled));
+            }
+            Some(serde_yaml::Value::Mapping(mapping)) => {
+                for (key, value) in mapping {
+                    if let Some(key) = key.as_str() {
+                        toc_options.insert(key.to_string(), value.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        settings
+    }
+}
+
+// 拆分头部元数据与正文，没有元数据时整篇都是正文
+pub fn split_front_matter(source: &str) -> anyhow::Result<(FrontMatter, &str)> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+
+    for delimiter in ["---", "+++"] {
+        let Some((header, body)) = split_block(source, delimiter) else {
+            continue;
+        };
+
+        let value = if delimiter == "---" {
+            serde_yaml::from_str::<serde_yaml::Value>(header)?
+        } else {
+            toml_to_yaml(toml::from_str::<toml::Value>(header)?)
+        };
+
+        // 空的头部块解析结果为 Null
+        let front_matter = if value.is_null() {
+            FrontMatter::default()
+        } else {
+            serde_yaml::from_value(value)?
+        };
+
+        return Ok((front_matter, body));
+    }
+
+    Ok((FrontMatter::default(), source))
+}
+
+// 找到以分隔符单独成行包围的头部块
+fn split_block<'a>(source: &'a str, delimiter: &str) -> Option<(&'a str, &'a str)> {
+    let first_line_end = source.find('\n')?;
+    if source[..first_line_end].trim_end() != delimiter {
+        return None;
+    }
+
+    let rest = &source[first_line_end + 1..];
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+// TOML 的日期时间类型无法直接反序列化为字符串，统一转换成 YAML 值再处理
+fn toml_to_yaml(value: toml::Value) -> serde_yaml::Value {
+    match value {
+        toml::Value::String(s) => serde_yaml::Value::String(s),
+        toml::Value::Integer(i) => serde_yaml::Value::Number(i.into()),
+        toml::Value::Float(f) => serde_yaml::Value::Number(f.into()),
+        toml::Value::Boolean(b) => serde_yaml::Value::Bool(b),
+        toml::Value::Datetime(d) => serde_yaml::Value::String(d.to_string()),
+        toml::Value::Array(array) => {
+            serde_yaml::Value::Sequence(array.into_iter().map(toml_to_yaml).collect())
+        }
+        toml::Value::Table(table) => serde_yaml::Value::Mapping(
+            table
+                .into_iter()
+                .map(|(k, v)| (serde_yaml::Value::String(k), toml_to_yaml(v)))
+                .collect(),
+        ),
+    }
+}
+
+// 允许 `tags: rust` 这种单个值的写法
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(s)) => vec![s],
+        Some(OneOrMany::Many(v)) => v,
+        None => Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_yaml_front_matter() {
+        let source = "---\ntitle: 布隆过滤器\ntags: rust\ndraft: true\n---\n# Body\n";
+        let (front_matter, body) = split_front_matter(source).unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("布隆过滤器"));
+        assert_eq!(front_matter.tags, ["rust"]);
+        assert!(front_matter.draft);
+        assert_eq!(body, "# Body\n");
+    }
+
+    #[test]
+    fn parses_toml_front_matter() {
+        let source = "+++\ntitle = \"Hello\"\ndate = 2024-01-02T03:04:05Z\ncategories = [\"a\", \"b\"]\n+++\nBody";
+        let (front_matter, body) = split_front_matter(source).unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hello"));
+        assert_eq!(front_matter.date.as_deref(), Some("2024-01-02T03:04:05Z"));
+        assert_eq!(front_matter.categories, ["a", "b"]);
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn skips_byte_order_mark_and_crlf() {
+        let source = "\u{feff}---\r\ntitle: Hello\r\n---\r\nBody";
+        let (front_matter, body) = split_front_matter(source).unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hello"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn empty_block_uses_defaults() {
+        let (front_matter, body) = split_front_matter("---\n---\nBody").unwrap();
+        assert!(front_matter.title.is_none());
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn unterminated_block_is_part_of_the_body() {
+        let source = "---\ntitle: Hello\n\nBody";
+        let (front_matter, body) = split_front_matter(source).unwrap();
+        assert!(front_matter.title.is_none());
+        assert_eq!(body, source);
+    }
+
+    #[test]
+    fn invalid_front_matter_is_an_error() {
+        assert!(split_front_matter("---\ntitle: [unclosed\n---\nBody").is_err());
+        assert!(split_front_matter("+++\ntitle = \n+++\nBody").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use kuchiki::NodeRef;
+use markup5ever::{namespace_url, ns, QualName};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+use crate::transform::{Transform, TransformContext};
+
+// 根据标题文字生成稳定的锚点，并在标题旁添加 "¶" 链接
+pub struct HeadingAnchors;
+
+impl Transform for HeadingAnchors {
+    fn name(&self) -> &'static str {
+        "heading_anchors"
+    }
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+        let options: AnchorOptions = context.options();
+        add_heading_anchors(document, &options)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnchorOptions {
+    // 用拼音代替汉字生成锚点
+    pub pinyin: bool,
+    // 是否添加 "¶" 链接
+    pub permalink: bool,
+}
+
+impl Default for AnchorOptions {
+    fn default() -> Self {
+        AnchorOptions {
+            pinyin: false,
+            permalink: true,
+        }
+    }
+}
+
+fn add_heading_anchors(_document: &NodeRef, options: &AnchorOptions) -> NodeRef {
+    let document = _document.clone();
+
+    let headings: Vec<NodeRef> = document
+        .select("h1, h2, h3, h4, h5, h6")
+        .unwrap()
+        .map(|node| node.as_node().clone())
+        .collect();
+
+    assign_heading_ids(&document, &headings, options.pinyin);
+
+    if options.permalink {
+        for heading in &headings {
+            let id = heading_id(heading).unwrap_or_default();
+
+            // 符号由样式表生成，不计入标题文字，目录与订阅源中不会出现
+            let link = NodeRef::new_element(QualName::new(None, ns!(html), "a".into()), None);
+            {
+                let element = link.as_element().unwrap();
+                let mut attributes = element.attributes.borrow_mut();
+                attributes.insert("class", "heading-anchor".to_string());
+                attributes.insert("href", format!("#{}", id));
+                attributes.insert("aria-label", "Permalink".to_string());
+            }
+            heading.append(link);
+        }
+    }
+
+    document
+}
+
+// 为没有 id 的标题生成锚点。`{#custom-id}` 写法指定的 id 保持不变，重复的锚点依次加上 -1、-2 后缀
+pub fn assign_heading_ids(document: &NodeRef, headings: &[NodeRef], pinyin: bool) {
+    let mut used: HashSet<String> = document
+        .select("[id]")
+        .unwrap()
+        .filter_map(|element| element.attributes.borrow().get("id").map(str::to_string))
+        .collect();
+
+    for heading in headings {
+        if heading_id(heading).is_some() {
+            continue;
+        }
+
+        let base = slugify(heading.text_contents().trim(), pinyin);
+        let mut id = base.clone();
+        let mut suffix = 1;
+        while used.contains(&id) {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+
+        used.insert(id.clone());
+        heading
+            .as_element()
+            .unwrap()
+            .attributes
+            .borrow_mut()
+            .insert("id", id);
+    }
+}
+
+fn heading_id(heading: &NodeRef) -> Option<String> {
+    heading
+        .as_element()?
+        .attributes
+        .borrow()
+        .get("id")
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+// 转为小写，保留字母、数字（包括汉字等），其余字符合并为连字符
+pub fn slugify(text: &str, pinyin: bool) -> String {
+    let text = if pinyin {
+        deunicode::deunicode(text)
+    } else {
+        text.to_string()
+    };
+
+    let mut slug = String::new();
+    let mut pending_dash = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' {
+            if pending_dash && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_dash = false;
+            slug.push(c);
+        } else {
+            pending_dash = true;
+        }
+    }
+
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kuchiki::traits::*;
+
+    fn ids(html: &str) -> Vec<String> {
+        let document = kuchiki::parse_html().one(html);
+        let headings: Vec<NodeRef> = document
+            .select("h1, h2, h3, h4, h5, h6")
+            .unwrap()
+            .map(|node| node.as_node().clone())
+            .collect();
+        assign_heading_ids(&document, &headings, false);
+        headings.iter().filter_map(heading_id).collect()
+    }
+
+    #[test]
+    fn slug_keeps_letters_and_joins_the_rest_with_dashes() {
+        assert_eq!(slugify("Hello, World!", false), "hello-world");
+        assert_eq!(slugify("  C++ 与 Rust  ", false), "c-与-rust");
+        assert_eq!(slugify("snake_case", false), "snake_case");
+    }
+
+    #[test]
+    fn pinyin_slug_and_empty_slug() {
+        assert_eq!(slugify("布隆过滤器", true), "bu-long-guo-lu-qi");
+        assert_eq!(slugify("!!!", false), "section");
+    }
+
+    #[test]
+    fn duplicate_headings_get_numbered_suffixes() {
+        assert_eq!(
+            ids("<h2>Setup</h2><h2>Setup</h2><h3>Setup</h3>"),
+            ["setup", "setup-1", "setup-2"]
+        );
+    }
+
+    #[test]
+    fn custom_ids_are_kept_and_reserved() {
+        // `## Setup {#setup}` 渲染为带 id 的标题；其他元素已有的 id 同样不会被重复使用
+        assert_eq!(
+            ids(r#"<h2>Intro</h2><h2 id="setup">Setup</h2><p id="intro-1"></p><h2>Setup</h2><h2>Intro</h2>"#),
+            ["intro", "setup", "setup-1", "intro-2"]
+        );
+    }
+}
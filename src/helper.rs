@@ -1,6 +1,11 @@
 use tokio::fs;
+use kuchiki::traits::*;
 use std::path::Path;
-use chrono::{DateTime, Local, NaiveDate, Datelike, Timelike};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Datelike, Timelike, TimeZone, Utc};
+use std::time::SystemTime;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::config::Timezone;
 
 pub async fn read_file(path: impl AsRef<Path>) -> String {
     match fs::read_to_string(path).await {
@@ -9,11 +14,120 @@ pub async fn read_file(path: impl AsRef<Path>) -> String {
     }
 }
 
+// 未设置 RUST_ENV=production 时即为开发模式
+pub fn is_development() -> bool {
+    std::env::var("RUST_ENV").map_or(true, |env| env != "production")
+}
+
+// 转义 HTML 特殊字符
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// 提取 HTML 片段中的纯文本，例如将渲染后的标题用于订阅源
+pub fn html_to_text(html: &str) -> String {
+    kuchiki::parse_html().one(html).text_contents().trim().to_string()
+}
+
+// 正文的纯文本，去掉脚注悬停提示与图表等不属于正文的内容，空白合并为一个空格
+pub fn body_text(html: &str) -> String {
+    let document = kuchiki::parse_html().one(html);
+    let removed: Vec<_> = document
+        .select(".footnote-popover, .mermaid-diagram svg, script, style")
+        .unwrap()
+        .collect();
+    for node in removed {
+        node.as_node().detach();
+    }
+
+    document
+        .text_contents()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 截取前 `length` 个字符，超出时以省略号结尾
+pub fn excerpt(text: &str, length: usize) -> String {
+    match text.char_indices().nth(length) {
+        Some((index, _)) => format!("{}…", text[..index].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+// URL 中无需编码的字符（RFC 3986 unreserved），其余一律编码
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
 
-pub fn format_system_time(time: std::time::SystemTime) -> (String, String) {
-    // 将 SystemTime 转换为 chrono 的 DateTime
-    let datetime: DateTime<Local> = time.into();
+// 编码 URL 路径中的一段，用于标签、分类等任意文本
+pub fn encode_path_segment(segment: &str) -> String {
+    // `.` 与 `..` 会被浏览器当作相对路径处理
+    if segment == "." || segment == ".." {
+        return segment.replace('.', "%2E");
+    }
+    utf8_percent_encode(segment, PATH_SEGMENT).to_string()
+}
+
+// 编码由 `/` 分隔的多段路径，保留分隔符
+pub fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(encode_path_segment)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// 文章页面的地址，id 可以包含子目录，例如 `notes/data-structures/bloom-filter`
+pub fn article_path(id: &str) -> String {
+    format!("/articles/{}", encode_path(id))
+}
+
+// 解析头部元数据中的日期，支持 RFC 3339 以及常见的 `YYYY-MM-DD [HH:MM[:SS]]` 写法，
+// 后者按站点配置的时区解释
+pub fn parse_date(value: &str, timezone: Timezone) -> Option<SystemTime> {
+    let value = value.trim();
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.into());
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })?;
+
+    timezone.resolve_local(&naive)
+}
+
+pub fn format_system_time(time: std::time::SystemTime, timezone: Timezone) -> (String, String) {
+    // 将 SystemTime 转换为站点时区的 DateTime
+    match timezone {
+        Timezone::Local => format_datetime(DateTime::<Local>::from(time)),
+        Timezone::Fixed(offset) => format_datetime(DateTime::<Utc>::from(time).with_timezone(&offset)),
+    }
+}
 
+fn format_datetime<Tz: TimeZone>(datetime: DateTime<Tz>) -> (String, String)
+where
+    Tz::Offset: std::fmt::Display,
+{
     // 格式化为英文格式
     let english_format = format!(
         "{}, {}, {} {}. {}",
@@ -104,14 +218,12 @@ fn number_to_chinese(num: u32) -> String {
 fn chinese_time(hour: u32, minute: u32) -> String {
     let period = match hour {
         23.. => "深夜",
-        0..=2 => "凌晨",
-        2..=7 => "凌晨",
-        7..=12 => "上午",
-        12..=15 => "中午",
-        15..=18 => "下午",
-        18..=20 => "傍晚",
-        20..=23 => "晚上",
-        _ => unreachable!(), // 不可能到达的分支
+        0..=7 => "凌晨",
+        8..=12 => "上午",
+        13..=15 => "中午",
+        16..=18 => "下午",
+        19..=20 => "傍晚",
+        21..=22 => "晚上",
     };
 
     // 将小时和分钟转换为汉字
@@ -0,0 +1,112 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag, TagEnd};
+use std::sync::{Arc, LazyLock};
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+use crate::{helper, theme::Theme};
+
+static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+// 高亮结果只带类名，颜色由主题样式表决定
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+// 这些代码块交给页面中的脚本渲染，保留原文
+const CLIENT_RENDERED: [&str; 2] = ["mermaid", "abc"];
+
+// 代码块信息串中的语言名，例如 "rust,ignore" 中的 "rust"
+fn language(info: &str) -> &str {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+}
+
+// 查找可以高亮的语法，未知语言或由客户端渲染的代码块返回 None
+fn find_syntax(lang: &str) -> Option<&'static SyntaxReference> {
+    if lang.is_empty() || CLIENT_RENDERED.contains(&lang) {
+        return None;
+    }
+    SYNTAX_SET.find_syntax_by_token(lang)
+}
+
+// 把 Markdown 事件流中可识别语言的代码块替换为高亮后的 HTML
+pub fn highlight_code_blocks<'a>(events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+    let mut output = Vec::new();
+    // 正在收集的代码块：语言、语法与代码
+    let mut current: Option<(String, &SyntaxReference, String)> = None;
+
+    for event in events {
+        match (event, &mut current) {
+            (Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))), None) => {
+                let lang = language(&info);
+                match find_syntax(lang) {
+                    Some(syntax) => current = Some((lang.to_string(), syntax, String::new())),
+                    None => output.push(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))),
+                }
+            }
+            (Event::Text(text), Some((_, _, code))) => code.push_str(&text),
+            (Event::End(TagEnd::CodeBlock), Some(_)) => {
+                let (lang, syntax, code) = current.take().unwrap();
+                output.push(Event::Html(CowStr::from(highlight_code_block(
+                    &lang, syntax, &code,
+                ))));
+            }
+            (event, _) => output.push(event),
+        }
+    }
+
+    output
+}
+
+// 把代码块渲染为带类名的 HTML
+fn highlight_code_block(lang: &str, syntax: &SyntaxReference, code: &str) -> String {
+    let mut generator =
+        ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAX_SET, CLASS_STYLE);
+
+    let highlighted = LinesWithEndings::from(code)
+        .try_for_each(|line| generator.parse_html_for_line_which_includes_newline(line));
+
+    let inner = match highlighted {
+        Ok(()) => generator.finalize(),
+        Err(e) => {
+            eprintln!("Error highlighting {} code block: {}", lang, e);
+            helper::escape_html(code)
+        }
+    };
+
+    format!(
+        r#"<pre class="hl-code"><code class="language-{}">{}</code></pre>
+"#,
+        helper::escape_html(lang),
+        inner
+    )
+}
+
+// 代码高亮主题的样式表。主题在 theme.toml 中分别指定浅色与深色配色使用的高亮主题，
+// 站点配置 highlight_theme 覆盖两者（见 Theme::load）
+pub fn theme_css(name: &str) -> String {
+    let theme = THEME_SET.themes.get(name).unwrap_or_else(|| {
+        eprintln!("Unknown highlight theme {}, using {}", name, DEFAULT_THEME);
+        &THEME_SET.themes[DEFAULT_THEME]
+    });
+
+    css_for_theme_with_class_style(theme, CLASS_STYLE).unwrap_or_default()
+}
+
+pub async fn light_css_handler(State(theme): State<Arc<Theme>>) -> impl IntoResponse {
+    css_response(theme_css(&theme.highlight.light))
+}
+
+pub async fn dark_css_handler(State(theme): State<Arc<Theme>>) -> impl IntoResponse {
+    css_response(theme_css(&theme.highlight.dark))
+}
+
+fn css_response(css: String) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], css)
+}
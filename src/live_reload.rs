@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use std::convert::Infallible;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::helper;
+
+// 开发模式下，文章重新渲染后通知浏览器刷新
+#[derive(Debug, Clone)]
+pub struct LiveReload {
+    tx: broadcast::Sender<String>,
+}
+
+impl Default for LiveReload {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(16);
+        LiveReload { tx }
+    }
+}
+
+impl LiveReload {
+    // 通知正在浏览该文章的页面刷新，没有订阅者时直接忽略
+    pub fn notify(&self, id: &str) {
+        let _ = self.tx.send(id.to_string());
+    }
+}
+
+// 每个文章页通过 SSE 订阅自己的更新
+pub async fn events_handler(
+    Path(id): Path<String>,
+    State(live_reload): State<LiveReload>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(live_reload.tx.subscribe()).filter_map(move |changed| {
+        match changed {
+            Ok(changed) if changed == id => Some(Ok(Event::default().data("reload"))),
+            // 消息积压时无法确定是否错过了本文的更新，保守起见也刷新
+            Err(_) => Some(Ok(Event::default().data("reload"))),
+            Ok(_) => None,
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+// 在文章页末尾注入订阅更新的脚本
+pub fn inject_client(html: &str, id: &str) -> String {
+    let script = format!(
+        r#"<script>
+    new EventSource("/__livereload/{}").onmessage = () => location.reload();
+</script>
+"#,
+        helper::encode_path(id)
+    );
+
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{}{}", &html[..index], script, &html[index..]),
+        None => format!("{}{}", html, script),
+    }
+}
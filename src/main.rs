@@ -1,205 +1,668 @@
 use axum::{
-    extract::Path,
+    extract::{FromRef, Path, Query, Request, State},
     http::StatusCode,
-    response::Html,
+    middleware,
+    response::{Html, IntoResponse, Response},
     routing::get,
     Router,
 };
-use footnote::process_footnote;
+use bundle::Bundle;
+use config::Config;
+use front_matter::split_front_matter;
+use pagination::{page_path, paginate, render_pagination, PageQuery};
+use publish_date::{resolve_publish_date, DateSource};
+use serde::Deserialize;
+use live_reload::LiveReload;
+use redirect::{RedirectMap, Redirects};
+use minijinja::context;
+use search::SearchIndex;
+use template::{ArticleContext, Templates};
+use theme::Theme;
 use kuchiki::{parse_html, traits::*, NodeRef};
 use pulldown_cmark::{Options, Parser};
-use table_of_contents::enable_table_of_contents;
+use transform::{run_pipeline, TransformContext, TransformSettings};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path as FsPath, PathBuf},
     sync::Arc,
     time::SystemTime,
 };
 use tokio::sync::RwLock;
-use tokio::fs::File;
-use tokio::io::AsyncReadExt;
-use tower_http::services::ServeDir;
+use tower_http::services::{ServeDir, ServeFile};
 use std::env;
 mod helper;
+mod config;
 mod table_of_contents;
 mod footnote;
+mod front_matter;
+mod publish_date;
+mod pagination;
+mod taxonomy;
+mod feed;
+mod export;
+mod watcher;
+mod live_reload;
+mod highlight;
+mod math;
+mod mermaid;
+mod transform;
+mod heading_anchor;
+mod search;
+mod template;
+mod directory;
+mod redirect;
+mod theme;
+mod bundle;
+mod responsive_image;
+mod render_cache;
 
 type ArticleStore = Arc<RwLock<HashMap<String, Article>>>;
 
+// 各个处理函数共享的状态
+#[derive(Clone, FromRef)]
+struct AppState {
+    store: ArticleStore,
+    live_reload: LiveReload,
+    search: SearchIndex,
+    redirects: RedirectMap,
+    config: Arc<Config>,
+    theme: Arc<Theme>,
+    templates: Templates,
+}
+
 #[derive(Debug, Clone)]
 struct Article {
     title: String,
+    // 正文 HTML，包括目录，由 article.html 模板套上页面结构
     content: String,
+    // 正文中不含目录的部分，供订阅源使用
+    body_html: String,
+    // 页面的 meta description：摘要，或正文开头的一段文字
+    description: String,
     file_path: PathBuf,
     last_modified: SystemTime,
     created_at: SystemTime,
+    date_source: DateSource,
+    // 以下字段来自头部元数据
+    updated: Option<SystemTime>,
+    tags: Vec<String>,
+    categories: Vec<String>,
+    summary: Option<String>,
+    draft: bool,
+    // 替换文件名部分的地址，例如 `notes/布隆过滤器.md` 设置 `slug: bloom-filter` 后为 `notes/bloom-filter`
+    slug: Option<String>,
+    // 解码后的旧地址，如 `/articles/old-name`
+    aliases: Vec<String>,
+    // 文章包（`trip/index.md`）所在的目录，其中的其他文件在文章地址之下提供
+    bundle_dir: Option<PathBuf>,
+    // 正文中的图片生成的各个版本，位于 responsive_image::CACHE_DIR
+    images: Vec<String>,
+}
+
+impl Article {
+    fn is_published(&self) -> bool {
+        publish_date::is_published(self.draft, self.created_at)
+    }
+
+    fn is_visible(&self) -> bool {
+        publish_date::is_visible(self.draft, self.created_at)
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    
-    let article_store = init_article_store().await?;
 
-    let app = Router::new()
+    // `blog build --out dist/` 导出静态站点，否则启动服务器。两者都接受 `--port`、`--base-url` 等覆盖配置的参数
+    let args: Vec<String> = env::args().skip(1).collect();
+    let build = args.first().map(String::as_str) == Some("build");
+    let (config, rest) = Config::load(if build { &args[1..] } else { &args })?;
+    let config = Arc::new(config);
+    let theme = Arc::new(Theme::load(&config)?);
+    let templates = Templates::new(&config, &theme);
+
+    if build {
+        return export::run(&rest, &config, &theme, &templates).await;
+    }
+    if let Some(arg) = rest.first() {
+        anyhow::bail!("unknown argument: {}", arg);
+    }
+
+    let article_store = init_article_store(&config, &theme).await?;
+    let live_reload = LiveReload::default();
+    let search_index: SearchIndex = Arc::new(RwLock::new(search::Index::build(
+        &*article_store.read().await,
+    )));
+    let mut redirects = Redirects::load(&config.redirects_file)?;
+    redirects.set_aliases(redirect::aliases(&*article_store.read().await));
+    let redirects: RedirectMap = Arc::new(RwLock::new(redirects));
+    watcher::spawn(
+        article_store.clone(),
+        search_index.clone(),
+        redirects.clone(),
+        live_reload.clone(),
+        config.clone(),
+        theme.clone(),
+    )?;
+
+    let mut app = Router::new()
         .route("/", get(root_handler))
         .route("/articles", get(index_handler))
         .route("/articles/", get(index_handler))
-        .route("/articles/{id}", get(article_handler))        
-        .nest_service("/public", ServeDir::new("src/public"))
-        .fallback(fallback_handler)
-        .with_state(article_store);
+        .route("/articles/{*path}", get(article_handler))
+        .route("/public/highlight-light.css", get(highlight::light_css_handler))
+        .route("/public/highlight-dark.css", get(highlight::dark_css_handler))
+        .nest_service("/public", static_files(&theme))
+        .nest_service("/images", ServeDir::new(responsive_image::CACHE_DIR))
+        .fallback(fallback_handler);
+
+    if config.features.taxonomies {
+        app = app
+            .route("/tags", get(taxonomy::tags_handler))
+            .route("/tags/{tag}", get(taxonomy::tag_handler))
+            .route("/tags/{tag}/page/{page}", get(taxonomy::tag_page_handler))
+            .route("/categories", get(taxonomy::categories_handler))
+            .route("/categories/{category}", get(taxonomy::category_handler))
+            .route("/categories/{category}/page/{page}", get(taxonomy::category_page_handler));
+    }
+
+    if config.features.feeds {
+        app = app
+            .route("/feed.xml", get(feed::rss_handler))
+            .route("/atom.xml", get(feed::atom_handler));
+        if config.features.taxonomies {
+            app = app
+                .route("/tags/{tag}/feed.xml", get(feed::tag_rss_handler))
+                .route("/tags/{tag}/atom.xml", get(feed::tag_atom_handler));
+        }
+    }
 
-    let env = env::var("RUST_ENV").unwrap_or_else(|_| "development".to_string());
+    if config.features.search {
+        app = app
+            .route("/search", get(search::search_handler))
+            .route("/api/search", get(search::api_search_handler));
+    }
+
+    // 开发模式下启用自动刷新
+    if helper::is_development() && config.features.live_reload {
+        app = app.route("/__livereload/{*id}", get(live_reload::events_handler));
+    }
 
-    let port = match env.as_str() {
-        "production" => 80,
-        _ => 3000,
+    let port = config.port();
+    let listener = tokio::net::TcpListener::bind((config.bind.as_str(), port)).await?;
+
+    let state = AppState {
+        store: article_store,
+        live_reload,
+        search: search_index,
+        redirects,
+        config,
+        theme,
+        templates,
     };
+    // 别名与旧地址在路由之前重定向
+    let app = app
+        .layer(middleware::from_fn_with_state(state.clone(), redirect::redirect_layer))
+        .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
     println!("Server running on http://localhost:{}", port);
     axum::serve(listener, app).await?;
 
     Ok(())
 }
 
-async fn root_handler() -> Result<Html<String>, StatusCode> {
-    let html = helper::read_file("src/public/index.html").await;
-    Ok(Html(html))
+// 依次在站点与主题的静态目录中查找，前面的目录覆盖后面的同名文件
+fn static_files(theme: &Theme) -> Router {
+    theme
+        .static_dirs
+        .iter()
+        .rev()
+        .fold(Router::new(), |fallback, dir| {
+            Router::new().fallback_service(ServeDir::new(dir).fallback(fallback))
+        })
 }
 
-async fn index_handler(
-    state: axum::extract::State<ArticleStore>,
-) -> Result<Html<String>, StatusCode> {
-    let mut store = state.write().await;
-    sync_articles_with_filesystem(&mut store).await; // 同步文章存储与文件系统
-
-    let mut articles: Vec<&Article> = store.values().collect();
-    articles.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-    let mut html = String::from("<h1>Articles</h1>");
-
-    for article in articles {
-        let (english_time, chinese_time) = helper::format_system_time(article.created_at);
-
-        html.push_str(&format!(
-            r#"<div class="card">
-                <h2><a href="/articles/{}">{}</a></h2>
-                <div class="time-container"><span>{}</span> <span>{}</span></div>
-            </div>"#,
-            article.file_path.file_stem().and_then(|s| s.to_str()).unwrap_or(""),
-            article.title,
-            english_time,
-            chinese_time
-        ));
-    }
-
-    let head = helper::read_file("src/head.html").await;
-    let all_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-{}
-<body>
-<main class="container">
-{}</main>
-</body>
-</html>"#,
-        head, html
-    );
+async fn root_handler(
+    State(theme): State<Arc<Theme>>,
+    State(templates): State<Templates>,
+) -> Result<Html<String>, (StatusCode, Html<String>)> {
+    let path = theme
+        .find_static("index.html")
+        .ok_or_else(|| template::not_found_response(&templates))?;
+    Ok(Html(templates.render_home(&helper::read_file(path).await)))
+}
 
-    Ok(Html(all_html))
+async fn index_handler(
+    Query(query): Query<PageQuery>,
+    state: State<ArticleStore>,
+    State(config): State<Arc<Config>>,
+    State(templates): State<Templates>,
+) -> Result<Html<String>, (StatusCode, Html<String>)> {
+    let store = state.read().await;
+
+    render_index(&store, query.page.unwrap_or(1), &config, &templates)
+        .map(Html)
+        .ok_or_else(|| template::not_found_response(&templates))
 }
 
+// 渲染文章列表的某一页，页码越界时返回 None
+fn render_index(
+    store: &HashMap<String, Article>,
+    page: usize,
+    config: &Config,
+    templates: &Templates,
+) -> Option<String> {
+    let page = paginate(sorted_articles(store), page, config.page_size)?;
+
+    let articles: Vec<ArticleContext> = page
+        .items
+        .iter()
+        .map(|(id, article)| ArticleContext::new(id, article, config))
+        .collect();
+
+    Some(templates.render(
+        "index.html",
+        context! {
+            section => "articles",
+            articles,
+            page => context! { current => page.current, total_pages => page.total_pages },
+            pagination => render_pagination(&page, |n| page_path("/articles", n)),
+        },
+    ))
+}
 
-async fn fallback_handler() -> Result<Html<String>, StatusCode> {
+// 按发布时间倒序排列的文章，不含隐藏的草稿与定时发布的文章
+fn sorted_articles(store: &HashMap<String, Article>) -> Vec<(&String, &Article)> {
+    let mut articles: Vec<(&String, &Article)> = store
+        .iter()
+        .filter(|(_, article)| article.is_visible())
+        .collect();
+    articles.sort_by_key(|(_, a)| std::cmp::Reverse(a.created_at));
+    articles
+}
 
-    let all_html = helper::read_file("src/public/404.html").await;
+// 完整的文章页面
+fn render_article(id: &str, article: &Article, config: &Config, templates: &Templates) -> String {
+    templates.render(
+        "article.html",
+        context! {
+            section => "articles",
+            article => ArticleContext::new(id, article, config),
+        },
+    )
+}
 
-    Ok(Html(all_html))
+async fn fallback_handler(State(templates): State<Templates>) -> (StatusCode, Html<String>) {
+    template::not_found_response(&templates)
 }
 
 // 初始化文章存储
-async fn init_article_store() -> anyhow::Result<ArticleStore> {
+async fn init_article_store(
+    config: &Arc<Config>,
+    theme: &Arc<Theme>,
+) -> anyhow::Result<ArticleStore> {
     let mut articles = HashMap::new();
-    let articles_dir = FsPath::new("articles");
-
-    if articles_dir.is_dir() {
-        for entry in std::fs::read_dir(articles_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("md") {
-                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                    match process_article(&path).await {
-                        Ok(article) => {
-                            articles.insert(stem.to_string(), article);
-                        }
-                        Err(e) => eprintln!("Error processing {}: {}", path.display(), e),
-                    }
-                }
+    let articles_dir = config.content_dir.as_path();
+    publish_date::load_first_commit_dates(articles_dir, config.timezone).await;
+
+    for path in article_files(articles_dir)? {
+        let Some(path_id) = article_id(articles_dir, &path) else {
+            continue;
+        };
+        match process_article(&path, config, theme).await {
+            Ok(article) => {
+                let id = resolve_id(&path_id, article.slug.as_deref());
+                insert_article(&mut articles, id, article).map_err(anyhow::Error::msg)?;
             }
+            Err(e) => eprintln!("Error processing {}: {}", path.display(), e),
         }
     }
 
+    // 已修改或删除的文章留下的旧缓存
+    render_cache::prune();
+
     Ok(Arc::new(RwLock::new(articles)))
 }
 
+// 递归列出文章目录下的所有文件，跳过以 `.` 开头的文件与目录；目录不存在时返回空列表
+fn article_files(dir: &FsPath) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_hidden(&path) {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(article_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn is_hidden(path: &FsPath) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+// 文章文件对应的 id，即相对文章目录、去掉 `.md` 的路径，如 `notes/data-structures/bloom-filter`；
+// 文章包的 `trip/index.md` 对应 `trip`。不是 Markdown 文件或不在文章目录中时返回 None
+fn article_id(articles_dir: &FsPath, path: &FsPath) -> Option<String> {
+    if path.extension().and_then(|s| s.to_str()) != Some("md") {
+        return None;
+    }
+
+    let relative = path.strip_prefix(articles_dir).ok()?;
+
+    let mut segments = Vec::new();
+    for component in relative.with_extension("").components() {
+        match component {
+            std::path::Component::Normal(segment) => {
+                let segment = segment.to_str()?;
+                if segment.starts_with('.') {
+                    return None;
+                }
+                segments.push(segment.to_string());
+            }
+            _ => return None,
+        }
+    }
+
+    if segments.len() > 1 && segments.last().is_some_and(|last| last == "index") {
+        segments.pop();
+    }
+    if segments.is_empty() {
+        return None;
+    }
+    Some(segments.join("/"))
+}
+
+// 文章最终使用的 id：设置了 slug 时替换文件名部分
+fn resolve_id(path_id: &str, slug: Option<&str>) -> String {
+    match (slug, directory::parent(path_id)) {
+        (None, _) => path_id.to_string(),
+        (Some(slug), Some(parent)) => format!("{}/{}", parent, slug),
+        (Some(slug), None) => slug.to_string(),
+    }
+}
+
+// 加入或更新文章，同一文件之前使用的 id（修改了 slug 时）一并移除并返回。
+// 地址或别名与其他文章冲突时不做修改，返回错误信息
+fn insert_article(
+    store: &mut HashMap<String, Article>,
+    id: String,
+    article: Article,
+) -> Result<Option<String>, String> {
+    if let Some(conflict) = redirect::find_conflict(store, &id, &article) {
+        return Err(conflict);
+    }
+
+    let previous = store
+        .iter()
+        .find(|(previous, existing)| **previous != id && existing.file_path == article.file_path)
+        .map(|(previous, _)| previous.clone());
+    if let Some(previous) = &previous {
+        store.remove(previous);
+    }
+    store.insert(id, article);
+    Ok(previous)
+}
+
+// 加入存储并更新搜索索引，返回发生变化的 id，包括因 slug 变化而移除的旧 id。
+// 与其他文章冲突时打印错误并忽略这次修改
+fn apply_article(
+    store: &mut HashMap<String, Article>,
+    index: &mut search::Index,
+    id: String,
+    article: Article,
+) -> Vec<String> {
+    match insert_article(store, id.clone(), article) {
+        Ok(previous) => {
+            index.update(&id, &store[&id]);
+            let mut changed = vec![id];
+            if let Some(previous) = previous {
+                index.remove(&previous);
+                changed.push(previous);
+            }
+            changed
+        }
+        Err(conflict) => {
+            eprintln!("Error: {}", conflict);
+            Vec::new()
+        }
+    }
+}
+
+// 自动生成的 meta description 的最大长度（字符数）
+const DESCRIPTION_LENGTH: usize = 150;
+
 // 处理单个文章文件
-async fn process_article(path: &PathBuf) -> anyhow::Result<Article> {
-    let content = std::fs::read_to_string(path)?;
-    let metadata = std::fs::metadata(path)?;
+async fn process_article(
+    path: &FsPath,
+    config: &Arc<Config>,
+    theme: &Arc<Theme>,
+) -> anyhow::Result<Article> {
+    let source = tokio::fs::read_to_string(path).await?;
+    let metadata = tokio::fs::metadata(path).await?;
     let last_modified = metadata.modified()?;
-    let created_at = metadata.created()?; // 新增：获取创建时间
-    let title = extract_title(&content); // 新增：提取标题
 
-    let html = generate_page(&content);
-    
+    let (front_matter, body) = split_front_matter(&source)?;
+    let slug = match front_matter.slug.as_deref().map(str::trim) {
+        Some(slug) if slug.is_empty() || slug.contains('/') || slug.starts_with('.') => {
+            anyhow::bail!("invalid slug `{}`: it must be a single non-empty path segment", slug)
+        }
+        slug => slug.map(str::to_string),
+    };
+    let aliases: Vec<String> = front_matter
+        .aliases
+        .iter()
+        .map(|alias| redirect::normalize_path(alias))
+        .collect();
+    for alias in &aliases {
+        redirect::check_source(alias).map_err(|e| anyhow::anyhow!("invalid alias: {}", e))?;
+    }
+    for tag in &front_matter.tags {
+        taxonomy::check_term(tag).map_err(|e| anyhow::anyhow!("invalid tag: {}", e))?;
+    }
+    for category in &front_matter.categories {
+        taxonomy::check_term(category).map_err(|e| anyhow::anyhow!("invalid category: {}", e))?;
+    }
+
+    let parse_date = |field: &str, value: Option<&str>| match value {
+        Some(value) => helper::parse_date(value, config.timezone)
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("invalid {}: {}", field, value)),
+        None => Ok(None),
+    };
+    let date = parse_date("date", front_matter.date.as_deref())?;
+    let updated = parse_date("updated", front_matter.updated.as_deref())?;
+    let (created_at, date_source) = resolve_publish_date(date, path, last_modified, config.timezone);
+
+    // 文章包中的相对地址需要知道文章最终的地址
+    let bundle_dir = bundle::bundle_dir(&config.content_dir, path);
+    let id = article_id(&config.content_dir, path)
+        .map(|path_id| resolve_id(&path_id, slug.as_deref()))
+        .unwrap_or_default();
+
+    // 源文件与配置都没有变化时直接使用上次的渲染结果
+    let cache_key = render_cache::key(&source, &id, bundle_dir.as_deref(), config, theme);
+    let rendered = match render_cache::load(&cache_key) {
+        Some(rendered) => rendered,
+        None => {
+            // Markdown 渲染、图片缩放、公式与 mermaid-cli 都是阻塞操作，放到专门的线程中执行
+            let body = body.to_string();
+            let front_matter = front_matter.clone();
+            let (id, bundle_dir) = (id.clone(), bundle_dir.clone());
+            let (config, theme) = (config.clone(), theme.clone());
+            let rendered = tokio::task::spawn_blocking(move || {
+                let bundle = bundle_dir.as_deref().map(|dir| Bundle { id: &id, dir });
+                render_body(&body, &front_matter, bundle.as_ref(), &config, &theme)
+            })
+            .await?;
+            // 有警告时（mmdc 执行失败、图片无法处理等）问题可能是暂时的，不写入缓存，下次重新渲染
+            if rendered.warnings.is_empty() {
+                render_cache::store(&cache_key, &rendered);
+            }
+            rendered
+        }
+    };
+    for warning in &rendered.warnings {
+        eprintln!("Warning ({}): {}", path.display(), warning);
+    }
+
     Ok(Article {
-        title: title.await,
-        content: html.await,
-        file_path: path.clone(),
+        title: rendered.title,
+        content: rendered.content,
+        body_html: rendered.body_html,
+        description: rendered.description,
+        file_path: path.to_path_buf(),
         last_modified,
         created_at,
+        date_source,
+        updated,
+        tags: front_matter.tags,
+        categories: front_matter.categories,
+        summary: front_matter.summary,
+        draft: front_matter.draft,
+        slug,
+        aliases,
+        bundle_dir,
+        images: rendered.images,
     })
 }
 
-async fn extract_title(content: &str) -> String {
-    let first_line = content.lines().next().unwrap_or("");
+// 渲染标题与正文，结果只取决于源文件、文章地址与配置，可以缓存
+fn render_body(
+    body: &str,
+    front_matter: &front_matter::FrontMatter,
+    bundle: Option<&Bundle<'_>>,
+    config: &Config,
+    theme: &Theme,
+) -> render_cache::Rendered {
+    // 优先使用头部元数据中的标题
+    let title = match &front_matter.title {
+        Some(title) => markdown_to_html(title),
+        None => extract_title(body),
+    };
+
+    let page = generate_page(body, &front_matter.transform_settings(), bundle, config, theme);
+
+    let description = match &front_matter.summary {
+        Some(summary) => helper::html_to_text(summary),
+        None => helper::excerpt(&helper::body_text(&page.body_html), DESCRIPTION_LENGTH),
+    };
+
+    render_cache::Rendered {
+        title,
+        content: page.html,
+        body_html: page.body_html,
+        description,
+        warnings: page.warnings,
+        images: page.images,
+        dependencies: page
+            .dependencies
+            .into_iter()
+            .map(|path| {
+                let state = render_cache::fingerprint(&path);
+                (path, state)
+            })
+            .collect(),
+    }
+}
+
+fn extract_title(content: &str) -> String {
+    let first_line = content.trim_start().lines().next().unwrap_or("");
     let title = first_line.trim_start_matches('#').trim();
-    markdown_to_html(title).await
+    // 去掉 `{#custom-id}` 这类标题属性
+    let title = match title.rfind('{') {
+        Some(start) if title.ends_with('}') => title[..start].trim_end(),
+        _ => title,
+    };
+    markdown_to_html(title)
+}
+
+// 渲染完成的文章正文
+struct RenderedPage {
+    // `.container` 中的全部内容
+    html: String,
+    // 不含目录的部分
+    body_html: String,
+    // 后处理过程中产生的警告
+    warnings: Vec<String>,
+    // 生成的图片文件名
+    images: Vec<String>,
+    // 渲染时检查过的文件，变化后需要重新渲染
+    dependencies: Vec<PathBuf>,
 }
 
-async fn generate_page(source: &String) -> String {
-    let head = helper::read_file("src/head.html").await;
-    let main = markdown_to_html(&source).await;
+// 页面的其余部分由模板生成，变换只作用于正文
+fn generate_page(
+    source: &str,
+    transforms: &TransformSettings,
+    bundle: Option<&Bundle<'_>>,
+    config: &Config,
+    theme: &Theme,
+) -> RenderedPage {
+    let main = markdown_to_html(source);
     let html = format!(
-        r#"<!DOCTYPE html>
-<html>
-{}
-<body>
-<main class="container">
+        r#"<main class="container">
 {}
-</main>
-</body>
-</html>"#,
-         head, main);
+</main>"#,
+         main);
     
-    post_process_html(html)
+    post_process_html(html, transforms, bundle, config, theme)
 }
 
-fn post_process_html(origin_html: String) -> String {
+// 依次执行启用的 HTML 变换（文章包中的地址、图片、目录、脚注、图表、公式等）
+fn post_process_html(
+    origin_html: String,
+    transforms: &TransformSettings,
+    bundle: Option<&Bundle>,
+    config: &Config,
+    theme: &Theme,
+) -> RenderedPage {
 
     let document = parse_html().one(origin_html);
 
-    let with_toc = enable_table_of_contents(&document);
-    let with_footnote = process_footnote(&with_toc);
-    let final_document = with_footnote;
-    let final_html = final_document.to_string();
+    let mut context = TransformContext::new(bundle, config, theme);
+    let final_document = run_pipeline(document, transforms, &mut context);
+
+    RenderedPage {
+        html: extract_main_content(&final_document, true),
+        body_html: extract_main_content(&final_document, false),
+        warnings: context.warnings,
+        images: context.images,
+        dependencies: context.dependencies,
+    }
+}
+
+// 取出 `.container` 中的内容，`with_toc` 为 false 时不含目录
+fn extract_main_content(document: &NodeRef, with_toc: bool) -> String {
+    let Some(container) = document.select(".container").unwrap().next() else {
+        return String::new();
+    };
 
-    final_html
+    container
+        .as_node()
+        .children()
+        .filter(|child| {
+            with_toc
+                || child
+                .as_element()
+                .and_then(|e| e.attributes.borrow().get("id").map(|id| id == "table-of-contents"))
+                != Some(true)
+        })
+        .map(|child| child.to_string())
+        .collect()
 }
 
 // Markdown转换HTML
-async fn markdown_to_html(content: &str) -> String {
+fn markdown_to_html(content: &str) -> String {
     let parser = Parser::new_ext(content,
         Options::ENABLE_MATH |
         Options::ENABLE_GFM |
@@ -209,111 +672,196 @@ async fn markdown_to_html(content: &str) -> String {
         Options::ENABLE_TABLES |
         Options::ENABLE_TASKLISTS |
         Options::ENABLE_FOOTNOTES |
-        Options::ENABLE_WIKILINKS
+        Options::ENABLE_WIKILINKS |
+        Options::ENABLE_HEADING_ATTRIBUTES
     );
+    let events = highlight::highlight_code_blocks(parser);
     let mut html_output = String::new();
-    pulldown_cmark::html::push_html(&mut html_output, parser);
+    pulldown_cmark::html::push_html(&mut html_output, events.into_iter());
 
     html_output
 }
 
 // 文章请求处理
+#[derive(Debug, Deserialize)]
+struct ArticleQuery {
+    preview: Option<String>,
+    page: Option<usize>,
+}
+
+// `/articles/` 之后的路径对应文章 id，如 `notes/data-structures/bloom-filter`；
+// 不是文章时依次作为文章包中的文件、目录（列出其中的子目录与文章）
 async fn article_handler(
-    Path(id): Path<String>,
-    state: axum::extract::State<ArticleStore>,
-) -> Result<Html<String>, StatusCode> {
-    let mut store = state.write().await;
-    
-    if let Some(article) = store.get_mut(&id) {
-
-        // 检查文件是否被修改
-        if let Ok(metadata) = tokio::fs::metadata(&article.file_path).await {
-            if let Ok(current_modified) = metadata.modified() {
-                if current_modified > article.last_modified {
-                    // 重新加载文章
-                    if let Ok(content) = tokio::fs::read_to_string(&article.file_path).await {
-                        article.title = extract_title(&content).await; // 更新标题
-                        article.content = generate_page(&content).await;
-                        article.last_modified = current_modified;
-                    }
+    Path(path): Path<String>,
+    Query(query): Query<ArticleQuery>,
+    state: State<ArticleStore>,
+    State(config): State<Arc<Config>>,
+    State(templates): State<Templates>,
+    request: Request,
+) -> Result<Response, (StatusCode, Html<String>)> {
+    // 文章由后台的文件监听任务保持最新，这里只需读取
+    let store = state.read().await;
+
+    // 草稿与未到发布时间的文章只在开发模式下，或带上 `?preview=<preview_token>` 时可以访问
+    let preview = config
+        .preview_token
+        .as_deref()
+        .is_some_and(|token| query.preview.as_deref() == Some(token));
+
+    // 以 `/` 结尾的地址只对应目录
+    let id = path.trim_end_matches('/');
+    if path.ends_with('/') || !store.contains_key(id) {
+        // 文章包中的文件与所属的文章一样，草稿中的文件同样需要预览参数
+        let asset = bundle::find_asset(&store, &path)
+            .filter(|(article, _)| preview || article.is_visible());
+        if let Some((_, file)) = asset {
+            drop(store);
+            return match ServeFile::new(file).try_call(request).await {
+                Ok(response) => Ok(response.into_response()),
+                Err(e) => {
+                    eprintln!("Error serving /articles/{}: {}", path, e);
+                    Err(template::not_found_response(&templates))
                 }
-            }        
+            };
+        }
+
+        // 文章列表的分页 `/articles/page/2`。与目录分页一样，同名的文章优先
+        if let Some(page) = id.strip_prefix("page/").and_then(|page| page.parse().ok()) {
+            return render_index(&store, page, &config, &templates)
+                .map(|html| Html(html).into_response())
+                .ok_or_else(|| template::not_found_response(&templates));
         }
-        
-        return Ok(Html(article.content.clone()));
+
+        let (dir, page) =
+            directory::split_page(id).unwrap_or((id, query.page.unwrap_or(1)));
+        return directory::render_directory(&store, dir, page, &config, &templates)
+            .map(|html| Html(html).into_response())
+            .ok_or_else(|| template::not_found_response(&templates));
     }
 
-    Err(StatusCode::NOT_FOUND)
+    let Some(article) = store
+        .get(id)
+        .filter(|article| preview || article.is_visible())
+    else {
+        return Err(template::not_found_response(&templates));
+    };
+
+    let html = render_article(id, article, &config, &templates);
+    if helper::is_development() && config.features.live_reload {
+        Ok(Html(live_reload::inject_client(&html, id)).into_response())
+    } else {
+        Ok(Html(html).into_response())
+    }
 }
 
 
-// 同步文章存储与文件系统
-async fn sync_articles_with_filesystem(store: &mut HashMap<String, Article>) {
-    let articles_dir = FsPath::new("articles");
+// 同步文章存储与文件系统，并更新搜索索引。与 watcher::apply_change 一样，渲染时不持有锁，
+// 全部渲染完成后才加写锁应用结果，读取不会被长时间阻塞
+async fn sync_articles_with_filesystem(
+    store: &ArticleStore,
+    index: &SearchIndex,
+    config: &Arc<Config>,
+    theme: &Arc<Theme>,
+) {
+    let articles_dir = config.content_dir.as_path();
+    if !articles_dir.is_dir() {
+        return;
+    }
 
-    if articles_dir.is_dir() {
-        // 检查现有文章
-        let existing_ids: Vec<String> = store.keys().cloned().collect();
-        for id in existing_ids {
-            if let Some(article) = store.get_mut(&id) {
-                // 检查文件是否还存在
-                if !article.file_path.exists() {
-                    store.remove(&id);
-                    continue;
-                }
+    // 现有文章的状态，取出后立即释放读锁
+    let existing: Vec<(String, PathBuf, SystemTime)> = store
+        .read()
+        .await
+        .iter()
+        .map(|(id, article)| (id.clone(), article.file_path.clone(), article.last_modified))
+        .collect();
+
+    let mut removed = Vec::new();
+    let mut changed = Vec::new();
+    for (id, file_path, last_modified) in &existing {
+        // 检查文件是否还存在
+        if !file_path.exists() {
+            removed.push(id.clone());
+            continue;
+        }
 
-                // 检查文件是否被修改
-                if let Ok(metadata) = tokio::fs::metadata(&article.file_path).await {
-                    if let Ok(current_modified) = metadata.modified() {
-                        if current_modified > article.last_modified {
-                            if let Ok(content) = tokio::fs::read_to_string(&article.file_path).await {
-                                // 更新标题
-                                let title = extract_title(&content).await;
-                                article.title = title;
-
-                                // 更新内容
-                                let html = generate_page(&content).await;
-                                article.content = html;
-
-                                // 更新修改时间
-                                article.last_modified = current_modified;
-                            }
-                        }
-                    }
-                }
-            }
+        // 检查文件是否被修改，修改过的重新处理，标题、内容与元数据一并更新；slug 变化时 id 随之改变
+        let modified = tokio::fs::metadata(file_path)
+            .await
+            .and_then(|metadata| metadata.modified());
+        if modified.is_ok_and(|modified| modified > *last_modified) {
+            changed.push(file_path.clone());
         }
+    }
 
-        // 检查是否有新文件
-        if let Ok(mut entries) = tokio::fs::read_dir(articles_dir).await {
-            while let Ok(Some(entry)) = entries.next_entry().await {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("md") {
-                    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                        if !store.contains_key(stem) {
-                            // 处理新文件
-                            if let Ok(content) = tokio::fs::read_to_string(&path).await {
-                                let title = extract_title(&content).await;
-                                let html = generate_page(&content).await;
-                                let metadata = tokio::fs::metadata(&path).await.unwrap();
-                                let last_modified = metadata.modified().unwrap();
-                                let created_at = metadata.created().unwrap();
-
-                                store.insert(
-                                    stem.to_string(),
-                                    Article {
-                                        title,
-                                        content: html,
-                                        file_path: path.clone(),
-                                        last_modified,
-                                        created_at,
-                                    },
-                                );
-                            }
-                        }
-                    }
-                }
-            }
+    // 检查是否有新文件，包括子目录中的文件
+    let known: HashSet<&PathBuf> = existing.iter().map(|(_, path, _)| path).collect();
+    let paths = article_files(articles_dir).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", articles_dir.display(), e);
+        Vec::new()
+    });
+    changed.extend(paths.into_iter().filter(|path| !known.contains(path)));
+
+    let mut rendered = Vec::new();
+    for path in changed {
+        let Some(path_id) = article_id(articles_dir, &path) else {
+            continue;
+        };
+        match process_article(&path, config, theme).await {
+            Ok(article) => rendered.push((resolve_id(&path_id, article.slug.as_deref()), article)),
+            Err(e) => eprintln!("Error processing {}: {}", path.display(), e),
         }
     }
+
+    // 先移除已删除的文章：设置了 slug 的文章改名后 id 不变
+    let mut store = store.write().await;
+    let mut index = index.write().await;
+    for id in removed {
+        store.remove(&id);
+        index.remove(&id);
+    }
+    for (id, article) in rendered {
+        apply_article(&mut store, &mut index, id, article);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(path: &str) -> Option<String> {
+        article_id(FsPath::new("articles"), FsPath::new(path))
+    }
+
+    #[test]
+    fn article_id_is_the_relative_path_without_extension() {
+        assert_eq!(id("articles/hello-world.md").as_deref(), Some("hello-world"));
+        assert_eq!(
+            id("articles/notes/data-structures/布隆过滤器.md").as_deref(),
+            Some("notes/data-structures/布隆过滤器")
+        );
+    }
+
+    #[test]
+    fn bundle_index_uses_the_directory_id() {
+        assert_eq!(id("articles/trip/index.md").as_deref(), Some("trip"));
+        assert_eq!(id("articles/notes/trip/index.md").as_deref(), Some("notes/trip"));
+        // 直接放在文章目录下的 index.md 是普通文章
+        assert_eq!(id("articles/index.md").as_deref(), Some("index"));
+    }
+
+    #[test]
+    fn slug_replaces_the_file_name() {
+        assert_eq!(resolve_id("notes/布隆过滤器", None), "notes/布隆过滤器");
+        assert_eq!(resolve_id("notes/布隆过滤器", Some("bloom-filter")), "notes/bloom-filter");
+        assert_eq!(resolve_id("hello-world", Some("hello")), "hello");
+    }
+
+    #[test]
+    fn other_files_have_no_article_id() {
+        assert_eq!(id("articles/trip/map.png"), None);
+        assert_eq!(id("articles/.drafts/hello.md"), None);
+        assert_eq!(id("articles/notes/.hidden.md"), None);
+        assert_eq!(id("other/hello.md"), None);
+    }
 }
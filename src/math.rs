@@ -0,0 +1,95 @@
+use kuchiki::{parse_html, traits::*, NodeRef};
+
+use crate::{
+    helper,
+    transform::{Transform, TransformContext},
+};
+
+// 把 pulldown-cmark 输出的 `span.math` 渲染为 MathML，无需浏览器端脚本
+pub struct Math;
+
+impl Transform for Math {
+    fn name(&self) -> &'static str {
+        "math"
+    }
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+        render_math(document, context)
+    }
+}
+
+fn render_math(_document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+    let document = _document.clone();
+
+    let formulas: Vec<NodeRef> = document
+        .select("span.math")
+        .unwrap()
+        .map(|node| node.as_node().clone())
+        .collect();
+
+    for formula in formulas {
+        let display = formula
+            .as_element()
+            .unwrap()
+            .attributes
+            .borrow()
+            .get("class")
+            .is_some_and(|class| class.split_whitespace().any(|c| c == "math-display"));
+        let tex = formula.text_contents();
+
+        let html = match render_tex(&tex, display) {
+            Ok(html) => html,
+            Err(e) => {
+                let message = error_message(&e);
+                context.warn(format!("{} in `{}`", message, tex.trim()));
+                error_marker(&tex, &message)
+            }
+        };
+
+        // 渲染结果是一段 HTML，解析后取出其中的节点替换原公式
+        let fragment = parse_html().one(html);
+        if let Ok(body) = fragment.select_first("body") {
+            let children: Vec<NodeRef> = body.as_node().children().collect();
+            for child in children {
+                formula.insert_before(child);
+            }
+        }
+        formula.detach();
+    }
+
+    document
+}
+
+fn render_tex(tex: &str, display: bool) -> Result<String, katex::Error> {
+    let opts = katex::Opts::builder()
+        .display_mode(display)
+        .output_type(katex::OutputType::Mathml)
+        .build()
+        .unwrap();
+
+    katex::render_with_opts(tex, &opts)
+}
+
+// KaTeX 的报错夹杂着脚本引擎的信息，只保留解析错误本身
+fn error_message(error: &katex::Error) -> String {
+    let message = error.to_string();
+    let Some(start) = message.find("KaTeX parse error: ") else {
+        return message;
+    };
+
+    let message = &message[start..];
+    let end = message
+        .find(" at position")
+        .or_else(|| message.find("\")"))
+        .unwrap_or(message.len());
+    message[..end].replace("\\\\", "\\")
+}
+
+// 公式有误时保留原文，并以醒目的样式标出错误
+fn error_marker(tex: &str, message: &str) -> String {
+    format!(
+        r#"<span class="math-error" title="{}">{}</span>"#,
+        helper::escape_html(message),
+        helper::escape_html(tex)
+    )
+}
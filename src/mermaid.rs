@@ -0,0 +1,148 @@
+use kuchiki::{parse_html, traits::*, NodeRef};
+use markup5ever::{namespace_url, ns, QualName};
+use sha2::{Digest, Sha256};
+use std::{fs, path::Path, process::Command};
+
+use crate::transform::{Transform, TransformContext};
+
+const CACHE_DIR: &str = "cache/mermaid";
+
+// 配置了 mermaid-cli（选项 `cli` 或站点配置 mermaid_cli，即 mmdc 的路径）后，
+// 在服务端把 Mermaid 代码块渲染为内联 SVG。未配置或渲染失败时保留原代码块，仍由页面中的脚本渲染
+pub struct Mermaid;
+
+impl Transform for Mermaid {
+    fn name(&self) -> &'static str {
+        "mermaid"
+    }
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+        match context
+            .option::<String>("cli")
+            .or_else(|| context.config.mermaid_cli.clone())
+        {
+            Some(cli) => render_mermaid(document, &cli, context),
+            None => document.clone(),
+        }
+    }
+}
+
+fn render_mermaid(_document: &NodeRef, cli: &str, context: &mut TransformContext) -> NodeRef {
+    let document = _document.clone();
+
+    let blocks: Vec<NodeRef> = document
+        .select("pre > code.language-mermaid")
+        .unwrap()
+        .map(|node| node.as_node().clone())
+        .collect();
+
+    for code in blocks {
+        let Some(pre) = code.parent() else {
+            continue;
+        };
+        let source = code.text_contents();
+
+        let svg = match cached_svg(cli, &source) {
+            Ok(svg) => svg,
+            Err(e) => {
+                context.warn(format!("failed to render diagram: {}", e));
+                continue;
+            }
+        };
+
+        pre.insert_before(diagram(&svg, &source));
+        pre.detach();
+    }
+
+    document
+}
+
+// 以内容哈希为键缓存渲染结果，图表不变时不必重新调用 mmdc
+fn cached_svg(cli: &str, source: &str) -> anyhow::Result<String> {
+    let hash = format!("{:x}", Sha256::digest(source.as_bytes()));
+    let cache_dir = Path::new(CACHE_DIR);
+    let svg_path = cache_dir.join(format!("{}.svg", hash));
+
+    if let Ok(svg) = fs::read_to_string(&svg_path) {
+        return Ok(svg);
+    }
+
+    fs::create_dir_all(cache_dir)?;
+    let input_path = cache_dir.join(format!("{}.mmd", hash));
+    fs::write(&input_path, source)?;
+
+    let result = run_cli(cli, &input_path, &svg_path, &hash[..12]);
+    let _ = fs::remove_file(&input_path);
+    result?;
+
+    Ok(fs::read_to_string(&svg_path)?)
+}
+
+fn run_cli(cli: &str, input: &Path, output: &Path, id: &str) -> anyhow::Result<()> {
+    let status = Command::new(cli)
+        .arg("--input")
+        .arg(input)
+        .arg("--output")
+        .arg(output)
+        .arg("--backgroundColor")
+        .arg("transparent")
+        // 同一页面中有多张图时，SVG 内部的 id 不能重复
+        .arg("--svgId")
+        .arg(format!("mermaid-{}", id))
+        .arg("--quiet")
+        .status()?;
+
+    if !status.success() {
+        let _ = fs::remove_file(output);
+        anyhow::bail!("{} exited with {}", cli, status);
+    }
+
+    Ok(())
+}
+
+// 图表与可折叠的原始代码
+fn diagram(svg: &str, source: &str) -> NodeRef {
+    let figure = new_element("figure");
+    figure
+        .as_element()
+        .unwrap()
+        .attributes
+        .borrow_mut()
+        .insert("class", "mermaid-diagram".to_string());
+
+    let fragment = parse_html().one(svg);
+    if let Ok(svg) = fragment.select_first("svg") {
+        figure.append(svg.as_node().clone());
+    }
+
+    let details = new_element("details");
+    details
+        .as_element()
+        .unwrap()
+        .attributes
+        .borrow_mut()
+        .insert("class", "mermaid-source".to_string());
+
+    let summary = new_element("summary");
+    summary.append(NodeRef::new_text("显示代码"));
+    details.append(summary);
+
+    // 换掉 language-mermaid 类名，避免页面脚本再次渲染
+    let pre = new_element("pre");
+    let code = new_element("code");
+    code.as_element()
+        .unwrap()
+        .attributes
+        .borrow_mut()
+        .insert("class", "language-mermaid-source".to_string());
+    code.append(NodeRef::new_text(source));
+    pre.append(code);
+    details.append(pre);
+
+    figure.append(details);
+    figure
+}
+
+fn new_element(name: &str) -> NodeRef {
+    NodeRef::new_element(QualName::new(None, ns!(html), name.into()), None)
+}
@@ -0,0 +1,119 @@
+use serde::Deserialize;
+
+// 列表页的分页参数，形如 `?page=2`
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub page: Option<usize>,
+}
+
+// 分页后的一页内容
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub current: usize,
+    pub total_pages: usize,
+    pub total_items: usize,
+}
+
+// 取出第 `current` 页（从 1 开始），页码越界时返回 None
+pub fn paginate<T>(items: Vec<T>, current: usize, per_page: usize) -> Option<Page<T>> {
+    let total_items = items.len();
+    let total_pages = total_items.div_ceil(per_page).max(1);
+
+    if current == 0 || current > total_pages {
+        return None;
+    }
+
+    let items = items
+        .into_iter()
+        .skip((current - 1) * per_page)
+        .take(per_page)
+        .collect();
+
+    Some(Page {
+        items,
+        current,
+        total_pages,
+        total_items,
+    })
+}
+
+// 分页地址：第一页即列表地址本身，其余为 `{base}/page/{n}`，静态导出时同样适用
+pub fn page_path(base_path: &str, n: usize) -> String {
+    if n <= 1 {
+        base_path.to_string()
+    } else {
+        format!("{}/page/{}", base_path.trim_end_matches('/'), n)
+    }
+}
+
+// 上一页、下一页与页码链接，`href` 根据页码生成链接地址
+pub fn render_pagination<T>(page: &Page<T>, href: impl Fn(usize) -> String) -> String {
+    let mut html = String::from(r#"<nav class="pagination">"#);
+    html.push_str(&format!(
+        r#"<span class="total">共 {} 篇 / {} in total</span>"#,
+        page.total_items, page.total_items
+    ));
+
+    if page.total_pages > 1 {
+        if page.current > 1 {
+            html.push_str(&format!(
+                r#"<a class="prev" href="{}">← Prev</a>"#,
+                href(page.current - 1)
+            ));
+        }
+
+        for n in 1..=page.total_pages {
+            if n == page.current {
+                html.push_str(&format!(r#"<span class="current">{}</span>"#, n));
+            } else {
+                html.push_str(&format!(r#"<a href="{}">{}</a>"#, href(n), n));
+            }
+        }
+
+        if page.current < page.total_pages {
+            html.push_str(&format!(
+                r#"<a class="next" href="{}">Next →</a>"#,
+                href(page.current + 1)
+            ));
+        }
+    }
+
+    html.push_str("</nav>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_items_into_pages() {
+        let page = paginate((1..=7).collect(), 2, 3).unwrap();
+        assert_eq!(page.items, [4, 5, 6]);
+        assert_eq!((page.current, page.total_pages, page.total_items), (2, 3, 7));
+
+        let last = paginate((1..=7).collect(), 3, 3).unwrap();
+        assert_eq!(last.items, [7]);
+    }
+
+    #[test]
+    fn page_zero_and_pages_past_the_end_do_not_exist() {
+        assert!(paginate((1..=7).collect::<Vec<_>>(), 0, 3).is_none());
+        assert!(paginate((1..=7).collect::<Vec<_>>(), 4, 3).is_none());
+    }
+
+    #[test]
+    fn empty_list_has_one_empty_page() {
+        let page = paginate(Vec::<u32>::new(), 1, 3).unwrap();
+        assert!(page.items.is_empty());
+        assert_eq!(page.total_pages, 1);
+        assert!(paginate(Vec::<u32>::new(), 2, 3).is_none());
+    }
+
+    #[test]
+    fn first_page_uses_the_list_path() {
+        assert_eq!(page_path("/articles", 1), "/articles");
+        assert_eq!(page_path("/articles/", 2), "/articles/page/2");
+        assert_eq!(page_path("/tags/rust", 3), "/tags/rust/page/3");
+    }
+}
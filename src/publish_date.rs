@@ -0,0 +1,149 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::SystemTime,
+};
+use serde::Serialize;
+use tokio::process::Command;
+
+use crate::{config::Timezone, helper};
+
+// 文章文件第一次加入仓库时的提交日期，见 load_first_commit_dates
+static FIRST_COMMIT_DATES: OnceLock<HashMap<PathBuf, SystemTime>> = OnceLock::new();
+
+// 发布日期的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateSource {
+    FrontMatter,
+    FileName,
+    Git,
+    Modified,
+}
+
+// 依次尝试：头部元数据日期、文件名中的日期、git 首次提交日期，最后才退回到修改时间
+pub fn resolve_publish_date(
+    front_matter_date: Option<SystemTime>,
+    path: &Path,
+    last_modified: SystemTime,
+    timezone: Timezone,
+) -> (SystemTime, DateSource) {
+    if let Some(date) = front_matter_date {
+        return (date, DateSource::FrontMatter);
+    }
+
+    if let Some(date) = date_from_file_name(path, timezone) {
+        return (date, DateSource::FileName);
+    }
+
+    if let Some(date) = FIRST_COMMIT_DATES.get().and_then(|dates| dates.get(path)) {
+        return (*date, DateSource::Git);
+    }
+
+    (last_modified, DateSource::Modified)
+}
+
+// 不是草稿且发布时间已到。定时发布的文章在每次请求时重新判断，到时间后自动出现
+pub fn is_published(draft: bool, date: SystemTime) -> bool {
+    !draft && date <= SystemTime::now()
+}
+
+// 文章列表、订阅源与搜索结果中是否显示；开发模式下草稿与定时发布的文章也显示
+pub fn is_visible(draft: bool, date: SystemTime) -> bool {
+    helper::is_development() || is_published(draft, date)
+}
+
+// 形如 `2025-03-01-foo.md` 的文件名
+fn date_from_file_name(path: &Path, timezone: Timezone) -> Option<SystemTime> {
+    let stem = path.file_stem()?.to_str()?;
+    let prefix = stem.get(..10)?;
+
+    match stem.as_bytes().get(10) {
+        None | Some(b'-') | Some(b'_') => helper::parse_date(prefix, timezone),
+        Some(_) => None,
+    }
+}
+
+// 启动时用一次 git log 读取文章目录中所有文件第一次加入仓库的日期，而不是每篇文章各运行一次。
+// 不在 git 仓库中时为空；启动后才提交的文件在下次启动前使用修改时间
+pub async fn load_first_commit_dates(content_dir: &Path, timezone: Timezone) {
+    let dates = first_commit_dates(content_dir, timezone)
+        .await
+        .unwrap_or_default();
+    let _ = FIRST_COMMIT_DATES.set(dates);
+}
+
+// 从最早的提交开始，记录每个文件被加入的日期；改名的文件沿用原来的日期，与 `git log --follow` 一致
+async fn first_commit_dates(
+    content_dir: &Path,
+    timezone: Timezone,
+) -> Option<HashMap<PathBuf, SystemTime>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(content_dir)
+        .args(["log", "--reverse", "-M", "--diff-filter=AR", "--name-status", "--relative", "-z"])
+        .arg("--format=%x00%aI")
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // `-z` 时各项以 NUL 分隔：提交日期，之后是 `A`、文件，或 `R100`、旧文件、新文件
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let mut fields = stdout
+        .split('\0')
+        .map(|field| field.trim_start_matches('\n'))
+        .filter(|field| !field.is_empty());
+
+    let mut dates = HashMap::new();
+    let mut date = None;
+    while let Some(field) = fields.next() {
+        if field == "A" {
+            let (Some(path), Some(date)) = (fields.next(), date) else {
+                continue;
+            };
+            dates.entry(content_dir.join(path)).or_insert(date);
+        } else if field.starts_with('R') {
+            let (Some(from), Some(to), Some(date)) = (fields.next(), fields.next(), date) else {
+                continue;
+            };
+            let first = dates.get(&content_dir.join(from)).copied().unwrap_or(date);
+            dates.insert(content_dir.join(to), first);
+        } else {
+            date = helper::parse_date(field, timezone);
+        }
+    }
+    Some(dates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, FixedOffset};
+
+    const UTC_8: Timezone = Timezone::Fixed(FixedOffset::east_opt(8 * 3600).unwrap());
+
+    fn date(path: &str) -> Option<SystemTime> {
+        date_from_file_name(Path::new(path), UTC_8)
+    }
+
+    #[test]
+    fn date_prefix_is_read_in_the_site_timezone() {
+        let expected: SystemTime = DateTime::parse_from_rfc3339("2025-03-01T00:00:00+08:00").unwrap().into();
+        assert_eq!(date("articles/2025-03-01-hello.md"), Some(expected));
+        assert_eq!(date("articles/notes/2025-03-01_hello.md"), Some(expected));
+        assert_eq!(date("articles/2025-03-01.md"), Some(expected));
+    }
+
+    #[test]
+    fn other_file_names_have_no_date() {
+        assert_eq!(date("articles/hello-world.md"), None);
+        assert_eq!(date("articles/2025-03-01hello.md"), None);
+        assert_eq!(date("articles/2025-13-01-hello.md"), None);
+        assert_eq!(date("articles/2025.md"), None);
+    }
+}
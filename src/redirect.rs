@@ -0,0 +1,299 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use percent_encoding::percent_decode_str;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::RwLock;
+
+use crate::{helper, Article, ArticleStore};
+
+pub type RedirectMap = Arc<RwLock<Redirects>>;
+
+// 文章改名或修改 slug 后自动记录的重定向，保存在 redirects_file 中，也可以手动编辑：
+//
+// ```toml
+// "/articles/布隆过滤器" = "/articles/notes/bloom-filter"
+// ```
+#[derive(Debug)]
+pub struct Redirects {
+    file: PathBuf,
+    map: BTreeMap<String, String>,
+    // 文章别名到文章 id，文章变化后由 set_aliases 整体替换
+    aliases: HashMap<String, String>,
+}
+
+impl Redirects {
+    // 文件不存在时从空表开始。旧地址不在 /articles/ 之下时报错
+    pub fn load(file: &Path) -> anyhow::Result<Redirects> {
+        let map: BTreeMap<String, String> = if file.is_file() {
+            let source = std::fs::read_to_string(file)
+                .map_err(|e| anyhow::anyhow!("Error reading {}: {}", file.display(), e))?;
+            toml::from_str::<BTreeMap<String, String>>(&source)
+                .map_err(|e| anyhow::anyhow!("Error parsing {}: {}", file.display(), e))?
+                .into_iter()
+                .map(|(from, to)| (normalize_path(&from), normalize_path(&to)))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        for from in map.keys() {
+            check_source(from).map_err(|e| anyhow::anyhow!("Error in {}: {}", file.display(), e))?;
+        }
+
+        Ok(Redirects {
+            file: file.to_path_buf(),
+            map,
+            aliases: HashMap::new(),
+        })
+    }
+
+    // 别名或已记录的旧地址对应的新地址（已编码）
+    pub fn target(&self, path: &str) -> Option<String> {
+        match self.aliases.get(path) {
+            Some(id) => Some(helper::article_path(id)),
+            None => self.map.get(path).map(|to| helper::encode_path(to)),
+        }
+    }
+
+    pub fn set_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.aliases = aliases;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.map.iter().map(|(from, to)| (from.as_str(), to.as_str()))
+    }
+
+    // 记录 `from` 已移动到 `to` 并写回文件。之前指向 `from` 的记录一并改为指向 `to`，
+    // 以 `to` 为起点的旧记录失效
+    pub fn insert(&mut self, from: &str, to: &str) {
+        if from == to {
+            return;
+        }
+
+        for target in self.map.values_mut() {
+            if target == from {
+                *target = to.to_string();
+            }
+        }
+        self.map.remove(to);
+        self.map.retain(|from, to| from != to);
+        self.map.insert(from.to_string(), to.to_string());
+
+        if let Err(e) = self.save() {
+            eprintln!("Error writing {}: {}", self.file.display(), e);
+        }
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let body = toml::to_string(&self.map)?;
+        std::fs::write(
+            &self.file,
+            format!("# 文章改名后自动记录的重定向（旧地址 = 新地址），可以手动编辑\n\n{}", body),
+        )?;
+        Ok(())
+    }
+}
+
+// 静态站点无法返回 301，改为在旧地址放置立即跳转的页面
+pub fn redirect_page(target: &str) -> String {
+    let target = helper::escape_html(target);
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Redirecting…</title>
+<link rel="canonical" href="{0}">
+<meta http-equiv="refresh" content="0; url={0}">
+</head>
+<body><a href="{0}">{0}</a></body>
+</html>
+"#,
+        target
+    )
+}
+
+// 统一为解码后、不带结尾 `/` 的路径。不以 `/` 开头时视为相对 /articles 的路径，
+// 例如别名 `old-name` 对应 `/articles/old-name`
+pub fn normalize_path(path: &str) -> String {
+    let decoded = percent_decode_str(path.trim()).decode_utf8_lossy();
+    let trimmed = decoded.trim_end_matches('/');
+    if !decoded.starts_with('/') {
+        format!("/articles/{}", trimmed)
+    } else if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+// 别名与重定向的旧地址只能位于 /articles/ 之下，不能是文章列表的分页地址，
+// 也不能含有空的、`.` 或 `..` 路径段：导出时它们会成为 out 目录下的文件
+pub fn check_source(path: &str) -> Result<(), String> {
+    let Some(rest) = path.strip_prefix("/articles/") else {
+        return Err(format!("{} is not under /articles/", path));
+    };
+    if rest.split('/').any(|segment| matches!(segment, "" | "." | "..")) {
+        return Err(format!("{} contains an empty, `.` or `..` segment", path));
+    }
+    if rest.starts_with("page/") {
+        return Err(format!("{} is the URL of an article list page", path));
+    }
+    Ok(())
+}
+
+// 所有文章的别名到文章 id
+pub fn aliases(store: &HashMap<String, Article>) -> HashMap<String, String> {
+    store
+        .iter()
+        .flat_map(|(id, article)| article.aliases.iter().map(move |alias| (alias.clone(), id.clone())))
+        .collect()
+}
+
+// 文章页面解码后的路径，与 normalize_path 的结果可以直接比较
+fn article_path(id: &str) -> String {
+    format!("/articles/{}", id)
+}
+
+// 检查文章的地址与别名是否和其他文章冲突，返回说明冲突的错误信息。同一文件的旧版本不算冲突
+pub fn find_conflict(store: &HashMap<String, Article>, id: &str, article: &Article) -> Option<String> {
+    let path = article_path(id);
+
+    for (other_id, other) in store {
+        if other.file_path == article.file_path {
+            continue;
+        }
+        let other_path = article_path(other_id);
+
+        if other_id == id {
+            return Some(format!(
+                "{} and {} both use the URL {}; give one of them a different `slug`",
+                article.file_path.display(),
+                other.file_path.display(),
+                path
+            ));
+        }
+        if other.aliases.contains(&path) {
+            return Some(format!(
+                "the URL {} of {} is an alias of {}",
+                path,
+                article.file_path.display(),
+                other.file_path.display()
+            ));
+        }
+        for alias in &article.aliases {
+            if *alias == other_path {
+                return Some(format!(
+                    "alias {} of {} is the URL of {}",
+                    alias,
+                    article.file_path.display(),
+                    other.file_path.display()
+                ));
+            }
+            if other.aliases.contains(alias) {
+                return Some(format!(
+                    "alias {} is used by both {} and {}",
+                    alias,
+                    article.file_path.display(),
+                    other.file_path.display()
+                ));
+            }
+        }
+    }
+
+    None
+}
+
+// 路由之前的一层：请求的地址是文章别名或已记录的旧地址时返回 301。现有文章的地址优先。
+// 别名与旧地址都在 /articles/ 之下，其他请求直接放行
+pub async fn redirect_layer(
+    State(store): State<ArticleStore>,
+    State(redirects): State<RedirectMap>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::GET && request.method() != Method::HEAD
+        || !request.uri().path().starts_with("/articles/")
+    {
+        return next.run(request).await;
+    }
+
+    let path = normalize_path(request.uri().path());
+    if let Some(id) = path.strip_prefix("/articles/") {
+        if store.read().await.contains_key(id) {
+            return next.run(request).await;
+        }
+    }
+
+    let target = redirects.read().await.target(&path);
+
+    match target {
+        Some(target) => {
+            let location = match request.uri().query() {
+                Some(query) => format!("{}?{}", target, query),
+                None => target,
+            };
+            (StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, location)]).into_response()
+        }
+        None => next.run(request).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_path_decodes_and_trims() {
+        assert_eq!(normalize_path("/articles/old/"), "/articles/old");
+        assert_eq!(normalize_path("/articles/%E5%B8%83%E9%9A%86"), "/articles/布隆");
+        assert_eq!(normalize_path(" old-name "), "/articles/old-name");
+        assert_eq!(normalize_path("notes/old"), "/articles/notes/old");
+        assert_eq!(normalize_path("/"), "/");
+    }
+
+    #[test]
+    fn sources_must_be_article_paths() {
+        assert!(check_source("/articles/old").is_ok());
+        assert!(check_source("/articles/notes/old").is_ok());
+        assert!(check_source("/").is_err());
+        assert!(check_source("/articles").is_err());
+        assert!(check_source("/tags/rust").is_err());
+        assert!(check_source("/articles/../../etc/passwd").is_err());
+        assert!(check_source("/articles/a//b").is_err());
+        assert!(check_source("/articles/page/2").is_err());
+    }
+
+    #[test]
+    fn insert_follows_chains_of_moves() {
+        let file = std::env::temp_dir().join(format!("blog-redirects-{}.toml", std::process::id()));
+        let mut redirects = Redirects::load(&file).unwrap();
+
+        redirects.insert("/articles/a", "/articles/b");
+        redirects.insert("/articles/b", "/articles/c");
+        assert_eq!(redirects.target("/articles/a").as_deref(), Some("/articles/c"));
+        assert_eq!(redirects.target("/articles/b").as_deref(), Some("/articles/c"));
+
+        // 改回原来的名字：指向自身的记录被移除，旧记录都指向新地址
+        redirects.insert("/articles/c", "/articles/a");
+        assert_eq!(redirects.target("/articles/a").as_deref(), None);
+        assert_eq!(redirects.target("/articles/b").as_deref(), Some("/articles/a"));
+        assert_eq!(redirects.target("/articles/c").as_deref(), Some("/articles/a"));
+
+        // 写回的文件可以重新读取
+        let reloaded = Redirects::load(&file).unwrap();
+        assert_eq!(
+            reloaded.iter().collect::<Vec<_>>(),
+            redirects.iter().collect::<Vec<_>>()
+        );
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use crate::{config::Config, responsive_image, theme::Theme};
+
+// 渲染结果保存在这里，每篇文章一个 JSON 文件，文件名是输入的哈希
+const CACHE_DIR: &str = "cache/articles";
+
+// 修改 Markdown 渲染、HTML 变换或图片处理的输出后递增，使旧的缓存全部失效
+const RENDERER_VERSION: &str = "2";
+
+// 本次运行中读取或写入过的缓存，其余的在 prune 时删除
+static USED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+// 从文章源文件得到的、与发布日期等文件系统信息无关的部分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rendered {
+    pub title: String,
+    pub content: String,
+    pub body_html: String,
+    pub description: String,
+    pub warnings: Vec<String>,
+    pub images: Vec<String>,
+    // 渲染时检查过的文件（文章包中引用的文件、生成响应式图片的原图）及其状态，
+    // 任何一个发生变化时缓存失效
+    pub dependencies: Vec<(PathBuf, Option<Fingerprint>)>,
+}
+
+// 文件的修改时间与大小，文件不存在时为 None
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    modified: SystemTime,
+    len: u64,
+}
+
+pub fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(Fingerprint {
+        modified: metadata.modified().ok()?,
+        len: metadata.len(),
+    })
+}
+
+// 缓存的键：文章源文件的全部内容、文章地址，以及会影响渲染结果的版本与配置
+pub fn key(
+    source: &str,
+    id: &str,
+    bundle_dir: Option<&Path>,
+    config: &Config,
+    theme: &Theme,
+) -> String {
+    // /public 下的图片按顺序在这些目录中查找
+    let static_dirs: Vec<_> = theme.static_dirs.iter().map(|dir| dir.to_string_lossy()).collect();
+    let mut hasher = Sha256::new();
+    for part in [
+        RENDERER_VERSION,
+        env!("CARGO_PKG_VERSION"),
+        // 站点的变换设置同样影响输出
+        &serde_json::to_string(&config.transforms).unwrap_or_default(),
+        config.mermaid_cli.as_deref().unwrap_or_default(),
+        &static_dirs.join("\n"),
+        if config.features.responsive_images { "images" } else { "" },
+        // 目录按钮的文字
+        &config.language,
+        id,
+        &bundle_dir.map(|dir| dir.to_string_lossy()).unwrap_or_default(),
+        source,
+    ] {
+        // 加上长度，避免相邻两部分拼接后产生歧义
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+// 读取缓存。依赖的文件发生变化，或生成的图片已被删除时视为未命中
+pub fn load(key: &str) -> Option<Rendered> {
+    let source = fs::read_to_string(entry_path(key)).ok()?;
+    let rendered: Rendered = serde_json::from_str(&source).ok()?;
+
+    let fresh = rendered
+        .dependencies
+        .iter()
+        .all(|(path, state)| fingerprint(path) == *state)
+        && rendered
+            .images
+            .iter()
+            .all(|name| Path::new(responsive_image::CACHE_DIR).join(name).is_file());
+    if !fresh {
+        return None;
+    }
+
+    USED.lock().unwrap().insert(key.to_string());
+    Some(rendered)
+}
+
+// 写入失败只影响下次启动的速度，打印错误后继续
+pub fn store(key: &str, rendered: &Rendered) {
+    USED.lock().unwrap().insert(key.to_string());
+    if let Err(e) = write_entry(key, rendered) {
+        eprintln!("Error writing render cache {}: {}", entry_path(key).display(), e);
+    }
+}
+
+// 先写入临时文件再改名，中断时不会留下不完整的缓存
+fn write_entry(key: &str, rendered: &Rendered) -> anyhow::Result<()> {
+    fs::create_dir_all(CACHE_DIR)?;
+    let path = entry_path(key);
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, serde_json::to_vec(rendered)?)?;
+    fs::rename(&temporary, &path)?;
+    Ok(())
+}
+
+fn entry_path(key: &str) -> PathBuf {
+    Path::new(CACHE_DIR).join(format!("{}.json", key))
+}
+
+// 删除本次运行没有用到的缓存，即已修改或删除的文章的旧结果。在载入全部文章之后调用
+pub fn prune() {
+    let Ok(entries) = fs::read_dir(CACHE_DIR) else {
+        return;
+    };
+    let used = USED.lock().unwrap();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let stale = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_none_or(|key| !used.contains(key));
+        if stale {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use kuchiki::NodeRef;
+use markup5ever::{namespace_url, ns, QualName};
+use percent_encoding::percent_decode_str;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    bundle::Bundle,
+    theme::Theme,
+    transform::{Transform, TransformContext},
+};
+
+// 生成的图片放在这里，以 /images 对外提供
+pub const CACHE_DIR: &str = "cache/images";
+
+// 缩放后的宽度（像素），大于原图的宽度会被跳过。正文最宽 800px，兼顾高分屏
+const WIDTHS: [u32; 4] = [480, 800, 1200, 1600];
+
+// 与 article.css 中 `.container` 的最大宽度一致
+const SIZES: &str = "(max-width: 800px) 100vw, 800px";
+
+// 修改缩放或编码参数后递增，使旧的缓存失效
+const VERSION: &str = "1";
+
+// 本地图片生成的一组文件
+struct Variants {
+    width: u32,
+    height: u32,
+    // 与原图格式相同的缩小版本，不含原图本身
+    fallback: Vec<(String, u32)>,
+    webp: Vec<(String, u32)>,
+}
+
+impl Variants {
+    fn names(&self) -> impl Iterator<Item = &str> {
+        self.fallback
+            .iter()
+            .chain(&self.webp)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+// 为正文中的图片加上懒加载与异步解码。本地的 PNG、JPEG 图片另外生成不同宽度的版本，
+// PNG 还生成 WebP 格式，输出 `<picture>`、`srcset` 与原图尺寸；带标题的图片改为 `<figure>`。
+// 不生成 AVIF：可用的编码器（rav1e）处理一张截图需要数秒到数十秒，会拖慢启动。
+// 关闭 `features.responsive_images` 时不做任何处理
+pub struct ResponsiveImages;
+
+impl Transform for ResponsiveImages {
+    fn name(&self) -> &'static str {
+        "responsive_images"
+    }
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+        if context.config.features.responsive_images {
+            let (images, warnings) =
+                process_images(document, context.bundle, context.theme, &mut context.dependencies);
+            context.images.extend(images);
+            context.warnings.extend(warnings);
+        }
+        document.clone()
+    }
+}
+
+// 返回用到的生成文件名（位于 CACHE_DIR）与警告，原图记录在 `dependencies` 中
+fn process_images(
+    document: &NodeRef,
+    bundle: Option<&Bundle>,
+    theme: &Theme,
+    dependencies: &mut Vec<PathBuf>,
+) -> (Vec<String>, Vec<String>) {
+    let mut images = Vec::new();
+    let mut warnings = Vec::new();
+
+    let elements: Vec<NodeRef> = document
+        .select("img")
+        .unwrap()
+        .map(|img| img.as_node().clone())
+        .collect();
+
+    for img in elements {
+        let element = img.as_element().unwrap();
+        let (src, title) = {
+            let mut attributes = element.attributes.borrow_mut();
+            attributes.insert("loading", "lazy".to_string());
+            attributes.insert("decoding", "async".to_string());
+            (
+                attributes.get("src").unwrap_or_default().to_string(),
+                attributes.get("title").map(str::to_string),
+            )
+        };
+
+        let mut node = img.clone();
+        if let Some(file) = source_file(&src, bundle, theme) {
+            dependencies.push(file.clone());
+            match variants(&file) {
+                Ok(Some(variants)) => {
+                    images.extend(variants.names().map(str::to_string));
+                    node = responsive(&img, &src, &variants);
+                }
+                Ok(None) => {}
+                Err(e) => warnings.push(format!("failed to resize {}: {}", file.display(), e)),
+            }
+        }
+
+        if let Some(title) = title.filter(|title| !title.trim().is_empty()) {
+            figure(&img, &node, &title);
+        }
+    }
+
+    (images, warnings)
+}
+
+// 静态目录（站点与主题，与 /public 的查找顺序一致）或文章包中的图片文件，其他地址返回 None
+fn source_file(src: &str, bundle: Option<&Bundle>, theme: &Theme) -> Option<PathBuf> {
+    let path = src.split(['?', '#']).next()?;
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+
+    let (dir, rest) = match path.strip_prefix("/public/") {
+        Some(rest) => (None, rest),
+        None => {
+            let bundle = bundle?;
+            let prefix = format!("/articles/{}/", bundle.id);
+            (Some(bundle.dir), path.strip_prefix(prefix.as_str())?)
+        }
+    };
+
+    if rest.split('/').any(|segment| segment.is_empty() || segment.starts_with('.')) {
+        return None;
+    }
+    match dir {
+        Some(dir) => Some(dir.join(rest)).filter(|file| file.is_file()),
+        None => theme.find_static(rest),
+    }
+}
+
+// 生成（或从缓存中取得）各个版本。GIF、SVG 等其他格式返回 None
+fn variants(file: &Path) -> anyhow::Result<Option<Variants>> {
+    // 按文件内容判断格式，扩展名可能与实际格式不符
+    let bytes = fs::read(file)?;
+    let format = match image::guess_format(&bytes) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg)) => format,
+        _ => return Ok(None),
+    };
+
+    // 以文件内容为键，图片修改后自动生成新的版本
+    let mut hasher = Sha256::new();
+    hasher.update(VERSION);
+    hasher.update(&bytes);
+    let hash = format!("{:x}", hasher.finalize());
+    let key = &hash[..16];
+
+    let (width, height) = image::ImageReader::new(std::io::Cursor::new(&bytes))
+        .with_guessed_format()?
+        .into_dimensions()?;
+
+    let extension = format.extensions_str()[0];
+    let mut variants = Variants {
+        width,
+        height,
+        fallback: Vec::new(),
+        webp: Vec::new(),
+    };
+
+    let cache_dir = Path::new(CACHE_DIR);
+    fs::create_dir_all(cache_dir)?;
+
+    // 只在缺少某个版本时解码原图
+    let mut decoded: Option<DynamicImage> = None;
+
+    let widths = WIDTHS
+        .into_iter()
+        .filter(|&w| w < width)
+        .chain(std::iter::once(width));
+    for w in widths {
+        let h = ((height as u64 * w as u64) / width as u64).max(1) as u32;
+
+        let mut outputs = Vec::new();
+        if w < width {
+            outputs.push((VariantFormat::Same(format), &mut variants.fallback));
+        }
+        // image 只提供无损的 WebP 编码，对照片而言往往比 JPEG 更大，因此只用于 PNG
+        if format == ImageFormat::Png {
+            outputs.push((VariantFormat::WebP, &mut variants.webp));
+        }
+
+        let mut resized: Option<DynamicImage> = None;
+        for (variant, list) in outputs {
+            let name = format!("{}-{}.{}", key, w, variant.extension(extension));
+            let path = cache_dir.join(&name);
+            if !path.is_file() {
+                if decoded.is_none() {
+                    decoded = Some(image::load_from_memory_with_format(&bytes, format)?);
+                }
+                let source = decoded.as_ref().unwrap();
+                let image = if w < width {
+                    &*resized.get_or_insert_with(|| source.resize_exact(w, h, FilterType::Lanczos3))
+                } else {
+                    source
+                };
+                encode(image, variant, &path)?;
+            }
+            list.push((name, w));
+        }
+    }
+
+    Ok(Some(variants))
+}
+
+#[derive(Clone, Copy)]
+enum VariantFormat {
+    Same(ImageFormat),
+    WebP,
+}
+
+impl VariantFormat {
+    fn extension(self, same: &str) -> &str {
+        match self {
+            VariantFormat::Same(_) => same,
+            VariantFormat::WebP => "webp",
+        }
+    }
+}
+
+// 先写入临时文件再改名，中断时不会留下不完整的缓存
+fn encode(image: &DynamicImage, format: VariantFormat, path: &Path) -> anyhow::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    {
+        let mut writer = BufWriter::new(fs::File::create(&temporary)?);
+        match format {
+            // JPEG 不支持透明通道
+            VariantFormat::Same(ImageFormat::Jpeg) => {
+                DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut writer, ImageFormat::Jpeg)?
+            }
+            VariantFormat::Same(format) => image.write_to(&mut writer, format)?,
+            VariantFormat::WebP => {
+                DynamicImage::ImageRgba8(image.to_rgba8()).write_to(&mut writer, ImageFormat::WebP)?
+            }
+        }
+    }
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+fn srcset(variants: &[(String, u32)]) -> impl Iterator<Item = String> + '_ {
+    variants
+        .iter()
+        .map(|(name, width)| format!("/images/{} {}w", name, width))
+}
+
+// 加上 `srcset` 与原图尺寸，原图作为最大的一档；有 WebP 版本时用 `<picture>` 包裹，
+// 浏览器按支持的格式与屏幕宽度选择。返回替换后位于原位置的节点
+fn responsive(img: &NodeRef, src: &str, variants: &Variants) -> NodeRef {
+    {
+        let mut attributes = img.as_element().unwrap().attributes.borrow_mut();
+        let fallback: Vec<String> = srcset(&variants.fallback)
+            .chain(std::iter::once(format!("{} {}w", src, variants.width)))
+            .collect();
+        attributes.insert("srcset", fallback.join(", "));
+        attributes.insert("sizes", SIZES.to_string());
+        attributes.insert("width", variants.width.to_string());
+        attributes.insert("height", variants.height.to_string());
+    }
+
+    if variants.webp.is_empty() {
+        return img.clone();
+    }
+
+    let picture = new_element("picture");
+    img.insert_before(picture.clone());
+    let source = new_element("source");
+    {
+        let mut attributes = source.as_element().unwrap().attributes.borrow_mut();
+        attributes.insert("type", "image/webp".to_string());
+        attributes.insert("srcset", srcset(&variants.webp).collect::<Vec<_>>().join(", "));
+        attributes.insert("sizes", SIZES.to_string());
+    }
+    picture.append(source);
+    img.detach();
+    picture.append(img.clone());
+    picture
+}
+
+// 段落中只有这一张图片时，把段落换成 `<figure>`，标题作为说明文字，不再作为鼠标悬停提示
+fn figure(img: &NodeRef, node: &NodeRef, title: &str) {
+    let Some(paragraph) = node.parent().filter(|parent| {
+        parent
+            .as_element()
+            .is_some_and(|element| &*element.name.local == "p")
+    }) else {
+        return;
+    };
+    let alone = paragraph.children().all(|child| {
+        child == *node || child.as_text().is_some_and(|text| text.borrow().trim().is_empty())
+    });
+    if !alone {
+        return;
+    }
+
+    let figure = new_element("figure");
+    paragraph.insert_before(figure.clone());
+    node.detach();
+    figure.append(node.clone());
+    let caption = new_element("figcaption");
+    caption.append(NodeRef::new_text(title));
+    figure.append(caption);
+    paragraph.detach();
+
+    img.as_element().unwrap().attributes.borrow_mut().remove("title");
+}
+
+fn new_element(name: &str) -> NodeRef {
+    NodeRef::new_element(QualName::new(None, ns!(html), name.into()), None)
+}
@@ -0,0 +1,471 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, Json},
+};
+use minijinja::context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::SystemTime,
+};
+use tokio::sync::RwLock;
+
+use crate::{
+    config::Config,
+    helper,
+    pagination::{paginate, render_pagination},
+    publish_date,
+    template::{self, DateText, Templates},
+    Article,
+};
+
+// 标题中的命中比正文中的命中重要得多
+const TITLE_WEIGHT: f64 = 5.0;
+// 摘要片段的长度（字符数）
+const SNIPPET_LENGTH: usize = 120;
+// JSON 接口默认返回的结果数
+const DEFAULT_LIMIT: usize = 20;
+
+pub type SearchIndex = Arc<RwLock<Index>>;
+
+// 倒排索引，随文章存储一同更新
+#[derive(Default)]
+pub struct Index {
+    documents: HashMap<String, Document>,
+    // 词项 → 文章 id → 出现次数
+    postings: HashMap<String, HashMap<String, Posting>>,
+}
+
+// 建立索引时保存的纯文本，用于生成摘要
+struct Document {
+    title: String,
+    text: String,
+    created_at: SystemTime,
+    draft: bool,
+    terms: HashSet<String>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Posting {
+    title: u32,
+    body: u32,
+}
+
+// 一条搜索结果
+pub struct Hit<'a> {
+    pub id: &'a str,
+    pub score: f64,
+    document: &'a Document,
+}
+
+impl Index {
+    pub(crate) fn build(store: &HashMap<String, Article>) -> Self {
+        let mut index = Index::default();
+        for (id, article) in store {
+            index.update(id, article);
+        }
+        index
+    }
+
+    pub(crate) fn update(&mut self, id: &str, article: &Article) {
+        self.remove(id);
+
+        let title = helper::html_to_text(&article.title);
+        let text = helper::body_text(&article.body_html);
+
+        let mut counts: HashMap<String, Posting> = HashMap::new();
+        for token in tokenize(&title, true) {
+            counts.entry(token).or_default().title += 1;
+        }
+        for token in tokenize(&text, true) {
+            counts.entry(token).or_default().body += 1;
+        }
+
+        let terms = counts.keys().cloned().collect();
+        for (term, posting) in counts {
+            self.postings
+                .entry(term)
+                .or_default()
+                .insert(id.to_string(), posting);
+        }
+
+        self.documents.insert(
+            id.to_string(),
+            Document {
+                title,
+                text,
+                created_at: article.created_at,
+                draft: article.draft,
+                terms,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        let Some(document) = self.documents.remove(id) else {
+            return;
+        };
+
+        for term in document.terms {
+            if let Some(postings) = self.postings.get_mut(&term) {
+                postings.remove(id);
+                if postings.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    // 返回包含全部查询词项的文章，按相关度排序
+    pub fn search(&self, query: &str) -> Vec<Hit<'_>> {
+        let terms: HashSet<String> = tokenize(query, false).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let total = self.documents.len() as f64;
+        // 逐个词项取交集，None 表示尚未处理任何词项
+        let mut scores: Option<HashMap<&str, f64>> = None;
+
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else {
+                return Vec::new();
+            };
+
+            let idf = (1.0 + total / postings.len() as f64).ln();
+            let mut next = HashMap::new();
+            for (id, posting) in postings {
+                let previous = match &scores {
+                    None => 0.0,
+                    Some(scores) => match scores.get(id.as_str()) {
+                        Some(&score) => score,
+                        None => continue,
+                    },
+                };
+
+                let body = if posting.body > 0 {
+                    1.0 + (posting.body as f64).ln()
+                } else {
+                    0.0
+                };
+                let weight = TITLE_WEIGHT * posting.title as f64 + body;
+                next.insert(id.as_str(), previous + idf * weight);
+            }
+            scores = Some(next);
+        }
+
+        let mut hits: Vec<Hit> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let (id, document) = self.documents.get_key_value(id)?;
+                // 草稿与定时发布的文章也建立索引，到发布时间后无需重建即可搜到
+                if !publish_date::is_visible(document.draft, document.created_at) {
+                    return None;
+                }
+                Some(Hit {
+                    id,
+                    score,
+                    document,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.document.created_at.cmp(&a.document.created_at))
+        });
+        hits
+    }
+}
+
+impl Hit<'_> {
+    pub fn url(&self) -> String {
+        helper::article_path(self.id)
+    }
+
+    pub fn title(&self) -> &str {
+        &self.document.title
+    }
+
+    // 高亮后的标题 HTML
+    pub fn highlighted_title(&self, query: &str) -> String {
+        highlight(&self.document.title.chars().collect::<Vec<_>>(), query)
+    }
+
+    // 正文中第一处命中附近的片段，命中的文字用 <mark> 标出
+    pub fn snippet(&self, query: &str) -> String {
+        let chars: Vec<char> = self.document.text.chars().collect();
+        let lower: Vec<char> = chars.iter().map(|&c| lowercase(c)).collect();
+
+        let first = match_ranges(&lower, &highlight_terms(query))
+            .into_iter()
+            .map(|(start, _)| start)
+            .min()
+            .unwrap_or(0);
+
+        let start = first.saturating_sub(SNIPPET_LENGTH / 4);
+        let end = (start + SNIPPET_LENGTH).min(chars.len());
+        let start = end.saturating_sub(SNIPPET_LENGTH).min(start);
+
+        let mut snippet = String::new();
+        if start > 0 {
+            snippet.push('…');
+        }
+        snippet.push_str(&highlight(&chars[start..end], query));
+        if end < chars.len() {
+            snippet.push('…');
+        }
+        snippet
+    }
+}
+
+// 中日韩文字没有空格分词，按相邻两个字切分；其他文字按非字母数字字符分词。
+// 建立索引时另外收录单字（`unigrams`），以便只搜索一个字
+fn tokenize(text: &str, unigrams: bool) -> impl Iterator<Item = String> {
+    let mut tokens = Vec::new();
+    for segment in segments(text) {
+        let chars: Vec<char> = segment.chars().collect();
+        if chars.len() > 1 && is_cjk(chars[0]) {
+            tokens.extend(chars.windows(2).map(|pair| pair.iter().collect()));
+            if unigrams {
+                tokens.extend(chars.iter().map(|c| c.to_string()));
+            }
+        } else {
+            tokens.push(segment);
+        }
+    }
+    tokens.into_iter()
+}
+
+// 按文字种类切分出的连续片段，已转为小写
+fn segments(text: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut current_cjk = false;
+
+    for c in text.chars().map(lowercase) {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                segments.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if !current.is_empty() && is_cjk(c) != current_cjk {
+            segments.push(std::mem::take(&mut current));
+        }
+        current_cjk = is_cjk(c);
+        current.push(c);
+    }
+
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'     // 平假名、片假名
+        | '\u{3400}'..='\u{4dbf}'   // 扩展 A
+        | '\u{4e00}'..='\u{9fff}'   // 基本汉字
+        | '\u{ac00}'..='\u{d7af}'   // 谚文
+        | '\u{f900}'..='\u{faff}'   // 兼容汉字
+        | '\u{20000}'..='\u{2ebef}' // 扩展 B 至 F
+    )
+}
+
+// 逐字转小写，保持字符位置不变，便于在原文中定位
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// 需要高亮的文字：查询中的各个片段，以及中文片段的两字词项
+fn highlight_terms(query: &str) -> Vec<Vec<char>> {
+    let mut terms: Vec<Vec<char>> = segments(query)
+        .into_iter()
+        .chain(tokenize(query, false))
+        .map(|term| term.chars().collect())
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+fn match_ranges(lower: &[char], terms: &[Vec<char>]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    for term in terms {
+        if term.is_empty() || term.len() > lower.len() {
+            continue;
+        }
+        for start in 0..=lower.len() - term.len() {
+            if lower[start..start + term.len()] == term[..] {
+                ranges.push((start, start + term.len()));
+            }
+        }
+    }
+    ranges
+}
+
+fn highlight(chars: &[char], query: &str) -> String {
+    let lower: Vec<char> = chars.iter().map(|&c| lowercase(c)).collect();
+    let mut marked = vec![false; chars.len()];
+    for (start, end) in match_ranges(&lower, &highlight_terms(query)) {
+        marked[start..end].fill(true);
+    }
+
+    let mut html = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let end = (i..chars.len())
+            .find(|&j| marked[j] != marked[i])
+            .unwrap_or(chars.len());
+        let text = helper::escape_html(&chars[i..end].iter().collect::<String>());
+        if marked[i] {
+            html.push_str(&format!("<mark>{}</mark>", text));
+        } else {
+            html.push_str(&text);
+        }
+        i = end;
+    }
+    html
+}
+
+// `/search?q=布隆&page=2`
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+pub async fn search_handler(
+    Query(query): Query<SearchQuery>,
+    State(index): State<SearchIndex>,
+    State(config): State<Arc<Config>>,
+    State(templates): State<Templates>,
+) -> Result<Html<String>, (StatusCode, Html<String>)> {
+    let index = index.read().await;
+    let q = query.q.trim();
+    let hits = index.search(q);
+    let page = paginate(hits, query.page.unwrap_or(1), config.page_size)
+        .ok_or_else(|| template::not_found_response(&templates))?;
+
+    let results: Vec<_> = page
+        .items
+        .iter()
+        .map(|hit| {
+            context! {
+                url => hit.url(),
+                title => hit.highlighted_title(q),
+                date => DateText::new(hit.document.created_at, &config),
+                snippet => hit.snippet(q),
+            }
+        })
+        .collect();
+
+    let encoded = helper::encode_path_segment(q);
+    let pagination = render_pagination(&page, |n| {
+        if n <= 1 {
+            format!("/search?q={}", encoded)
+        } else {
+            format!("/search?q={}&page={}", encoded, n)
+        }
+    });
+
+    Ok(Html(templates.render(
+        "search.html",
+        context! {
+            section => "search",
+            query => q,
+            results,
+            pagination,
+        },
+    )))
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    query: String,
+    total: usize,
+    results: Vec<SearchResult>,
+}
+
+#[derive(Serialize)]
+struct SearchResult {
+    id: String,
+    url: String,
+    title: String,
+    // 以下两项为 HTML，命中处用 <mark> 标出
+    title_html: String,
+    snippet: String,
+    score: f64,
+}
+
+pub async fn api_search_handler(
+    Query(query): Query<SearchQuery>,
+    State(index): State<SearchIndex>,
+) -> Json<SearchResponse> {
+    let index = index.read().await;
+    let q = query.q.trim();
+    let hits = index.search(q);
+    let total = hits.len();
+
+    let results = hits
+        .iter()
+        .take(query.limit.unwrap_or(DEFAULT_LIMIT))
+        .map(|hit| SearchResult {
+            id: hit.id.to_string(),
+            url: hit.url(),
+            title: hit.title().to_string(),
+            title_html: hit.highlighted_title(q),
+            snippet: hit.snippet(q),
+            score: hit.score,
+        })
+        .collect();
+
+    Json(SearchResponse {
+        query: q.to_string(),
+        total,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str, unigrams: bool) -> Vec<String> {
+        tokenize(text, unigrams).collect()
+    }
+
+    #[test]
+    fn cjk_text_is_split_into_bigrams() {
+        assert_eq!(tokens("布隆过滤器", false), ["布隆", "隆过", "过滤", "滤器"]);
+        assert_eq!(tokens("过滤器", true), ["过滤", "滤器", "过", "滤", "器"]);
+    }
+
+    #[test]
+    fn mixed_scripts_are_segmented_by_script() {
+        assert_eq!(segments("Rust实现布隆Filter, v2"), ["rust", "实现布隆", "filter", "v2"]);
+        assert_eq!(tokens("用Rust写博客", false), ["用", "rust", "写博", "博客"]);
+    }
+
+    #[test]
+    fn single_character_is_kept_as_a_token() {
+        assert_eq!(tokens("树", false), ["树"]);
+        assert_eq!(tokens("树", true), ["树"]);
+        assert_eq!(tokens("a", false), ["a"]);
+    }
+
+    #[test]
+    fn punctuation_and_whitespace_are_dropped() {
+        assert_eq!(segments("  ——你好，世界！ "), ["你好", "世界"]);
+        assert!(tokens("，。！", true).is_empty());
+    }
+}
@@ -1,13 +1,113 @@
-use kuchiki::{parse_html, traits::*, NodeRef};
+use kuchiki::NodeRef;
 use markup5ever::QualName;
+use serde::Deserialize;
 use std::collections::HashMap;
 use markup5ever::ns;
 use markup5ever::namespace_url;
 
-pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {   
+use crate::{
+    heading_anchor,
+    transform::{Transform, TransformContext},
+};
+
+// 为标题编号并生成目录
+pub struct TableOfContents;
+
+impl Transform for TableOfContents {
+    fn name(&self) -> &'static str {
+        "table_of_contents"
+    }
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef {
+        let mut options: TocOptions = context.options();
+        // 未单独指定时与站点的语言一致
+        if options.lang.is_none() {
+            options.lang = Some(context.config.language.clone());
+        }
+        enable_table_of_contents(document, &options)
+    }
+}
+
+// 目录的配置，站点级别写在 blog.toml 的 `[transforms.options.table_of_contents]` 中，
+// 单篇文章可以在头部元数据中用 `toc:` 覆盖
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TocOptions {
+    // 是否显示目录
+    pub enabled: bool,
+    // 目录包含的标题层级范围
+    pub min_level: u8,
+    pub max_level: u8,
+    // 是否在目录项与标题前显示章节编号
+    pub numbered: bool,
+    // 标题数少于该值时不生成目录
+    pub min_headings: usize,
+    pub placement: Placement,
+    // 折叠按钮的语言，默认为站点的 language，可以用下面两项单独指定文字
+    pub lang: Option<String>,
+    pub collapse_label: Option<String>,
+    pub expand_label: Option<String>,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        TocOptions {
+            enabled: true,
+            min_level: 1,
+            max_level: 6,
+            numbered: false,
+            min_headings: 1,
+            placement: Placement::Title,
+            lang: None,
+            collapse_label: None,
+            expand_label: None,
+        }
+    }
+}
+
+impl TocOptions {
+    fn labels(&self) -> (String, String) {
+        // `en-US` 等地区写法同样按英文处理
+        let lang = self.lang.as_deref().unwrap_or_default();
+        let (collapse, expand) = match lang.split(['-', '_']).next() {
+            Some("en") => ("Collapse 👇", "Expand 👆"),
+            _ => ("折叠目录 👇", "展开目录 👆"),
+        };
+
+        (
+            self.collapse_label.clone().unwrap_or_else(|| collapse.to_string()),
+            self.expand_label.clone().unwrap_or_else(|| expand.to_string()),
+        )
+    }
+}
+
+// 目录的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Placement {
+    // 第一个标题之后
+    Title,
+    // 宽屏时固定在正文侧边
+    Sidebar,
+    // Markdown 中 `[TOC]` 标记所在的位置，没有标记时退回到标题之后
+    Marker,
+}
+
+// 文中的 `[TOC]` 标记会被渲染成单独的段落
+const TOC_MARKER: &str = "[TOC]";
+
+fn enable_table_of_contents(_document: &NodeRef, options: &TocOptions) -> NodeRef {
 
     let document = _document.clone();
 
+    // 无论是否生成目录，都不应把标记原样显示出来
+    let markers: Vec<NodeRef> = document
+        .select("p")
+        .unwrap()
+        .filter(|p| p.text_contents().trim() == TOC_MARKER)
+        .map(|p| p.as_node().clone())
+        .collect();
+
     let headings: Vec<NodeRef> = document
         .select("h1, h2, h3, h4, h5, h6")
         .unwrap()
@@ -15,15 +115,20 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
         .collect();
 
     if headings.is_empty() {
+        detach_all(&markers);
         return document;
     }
 
+    // 通常已由 heading_anchors 设置，这里只补上缺少的 ID
+    heading_anchor::assign_heading_ids(&document, &headings, false);
+
     let mut counters: HashMap<u8, u32> = HashMap::new();
+    // 每个标题的章节编号，只计入目录范围内的层级
+    let mut section_numbers: Vec<Option<String>> = Vec::new();
 
-    // 遍历标题，生成编号并设置 ID
+    // 遍历标题，生成编号
     for heading in &headings {
-        let tag_name = heading.as_element().unwrap().name.local.to_string();
-        let level = tag_name.chars().nth(1).unwrap().to_digit(10).unwrap() as u8;
+        let level = heading_level(heading);
 
         // 重置更高层级的计数器
         for i in level + 1..=6 {
@@ -34,22 +139,26 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
         let count = counters.entry(level).or_insert(0);
         *count += 1;
 
-        // 生成编号
-        let mut number = String::new();
-        for i in 1..=level {
-            number.push_str(&counters.get(&i).unwrap_or(&0).to_string());
-            number.push('.');
+        if (options.min_level..=options.max_level).contains(&level) {
+            let section_number = (options.min_level..=level)
+                .map(|i| counters.get(&i).unwrap_or(&0).to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            section_numbers.push(Some(section_number));
+        } else {
+            section_numbers.push(None);
         }
-        number.pop(); // 移除最后一个点
+    }
 
-        // 设置标题 ID
-        let anchor_id = format!("{}-{}", tag_name, number);
-        heading
-            .as_element()
-            .unwrap()
-            .attributes
-            .borrow_mut()
-            .insert("id", anchor_id.to_string());
+    let entries: Vec<(&NodeRef, String)> = headings
+        .iter()
+        .zip(section_numbers)
+        .filter_map(|(heading, number)| number.map(|number| (heading, number)))
+        .collect();
+
+    if !options.enabled || entries.is_empty() || entries.len() < options.min_headings {
+        detach_all(&markers);
+        return document;
     }
 
     // 创建目录容器
@@ -62,11 +171,15 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
         .attributes
         .borrow_mut()
         .insert("id", "table-of-contents".to_string());
+    let toc_class = match options.placement {
+        Placement::Sidebar => "table-of-contents toc-sidebar",
+        _ => "table-of-contents",
+    };
     toc.as_element()
         .unwrap()
         .attributes
         .borrow_mut()
-        .insert("class", "table-of-contents".to_string());
+        .insert("class", toc_class.to_string());
 
     let toc_list = NodeRef::new_element(
         QualName::new(None, ns!(html), "ul".into()),
@@ -84,9 +197,10 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
     let mut current_toc_items = vec![toc_list.clone()];
 
     // 遍历标题，生成目录项
-    for heading in &headings {
-        let tag_name = heading.as_element().unwrap().name.local.to_string();
-        let level = tag_name.chars().nth(1).unwrap().to_digit(10).unwrap() as u8;
+    for (heading, number) in &entries {
+        let level = heading_level(heading);
+        // 相对于目录最高层级的深度，从 1 开始
+        let depth = level - options.min_level + 1;
 
         // 创建目录项
         let list_item = NodeRef::new_element(
@@ -98,7 +212,7 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
             .unwrap()
             .attributes
             .borrow_mut()
-            .insert("class", format!("toc-level-{}", level).to_string());
+            .insert("class", format!("toc-level-{}", depth).to_string());
 
         let link = NodeRef::new_element(
             QualName::new(None, ns!(html), "a".into()),
@@ -109,16 +223,23 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
             .attributes
             .borrow_mut()
             .insert("href", format!("#{}", heading.as_element().unwrap().attributes.borrow().get("id").unwrap()).to_string());
+        if options.numbered {
+            link.append(section_number(number));
+        }
         link.append(NodeRef::new_text(heading.text_contents().trim()));
         list_item.append(link);
 
+        if options.numbered {
+            heading.prepend(section_number(number));
+        }
+
         // 调整当前目录项栈
-        while current_toc_items.len() > level as usize {
+        while current_toc_items.len() > depth as usize {
             current_toc_items.pop();
         }
 
         // 处理子目录
-        if level > 1 {
+        if depth > 1 {
             let last_item = current_toc_items.last().unwrap();
             if last_item.select("ul").unwrap().next().is_none() {
                 let sub_list = NodeRef::new_element(
@@ -137,6 +258,8 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
 
     // 添加折叠按钮
     if let Some(first_toc_item) = toc_list.select("li").unwrap().next() {
+        let (collapse_label, expand_label) = options.labels();
+
         let toggle_button = NodeRef::new_element(
             QualName::new(None, ns!(html), "button".into()),
             None,
@@ -147,7 +270,7 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
             .attributes
             .borrow_mut()
             .insert("class", "toggle-btn".to_string());
-        toggle_button.append(NodeRef::new_text("折叠目录 👇"));
+        toggle_button.append(NodeRef::new_text(collapse_label.as_str()));
         toggle_button
             .as_element()
             .unwrap()
@@ -155,6 +278,14 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
             .borrow_mut()
             .insert("style", "margin-right: 10px; padding: 5px 10px; cursor: pointer; font-size: 1em;".to_string());
 
+        // 按钮文字可以配置，因此用 data 属性记录折叠状态，并保存两种状态下的文字
+        {
+            let element = toggle_button.as_element().unwrap();
+            let mut attributes = element.attributes.borrow_mut();
+            attributes.insert("data-collapse-label", collapse_label);
+            attributes.insert("data-expand-label", expand_label);
+        }
+
         toggle_button
             .as_element()
             .unwrap()
@@ -162,16 +293,18 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
             .borrow_mut()
             .insert("onclick", r#"
                 const subLists = document.querySelectorAll('.table-of-contents ul');
-                if (this.textContent.includes('折叠')) {
+                if (this.dataset.collapsed !== 'true') {
                     subLists.forEach(subList => {
                         subList.style.display = 'none';
                     });
-                    this.textContent = '展开目录 👆';
+                    this.dataset.collapsed = 'true';
+                    this.textContent = this.dataset.expandLabel;
                 } else {
                     subLists.forEach(subList => {
                         subList.style.display = 'block';
                     });
-                    this.textContent = '折叠目录 👇';
+                    this.dataset.collapsed = 'false';
+                    this.textContent = this.dataset.collapseLabel;
                 }
             "#.to_string());
 
@@ -184,11 +317,42 @@ pub fn enable_table_of_contents(_document: &NodeRef) -> NodeRef {
     }
 
     // 将目录插入到文档中
-    if let Some(_container) = document.select(".container").unwrap().next() {
-        if let Some(first_heading) = document.select("h1").unwrap().next() {
-            first_heading.as_node().insert_after(toc.clone());
+    if let Some(container) = document.select(".container").unwrap().next() {
+        match (options.placement, markers.first()) {
+            (Placement::Sidebar, _) => container.as_node().prepend(toc.clone()),
+            (Placement::Marker, Some(marker)) => marker.insert_before(toc.clone()),
+            _ => match document.select("h1").unwrap().next() {
+                Some(first_heading) => first_heading.as_node().insert_after(toc.clone()),
+                None => container.as_node().prepend(toc.clone()),
+            },
         }
     }
+    detach_all(&markers);
 
     document
 }
+
+fn heading_level(heading: &NodeRef) -> u8 {
+    let tag_name = heading.as_element().unwrap().name.local.to_string();
+    tag_name.chars().nth(1).unwrap().to_digit(10).unwrap() as u8
+}
+
+fn section_number(number: &str) -> NodeRef {
+    let span = NodeRef::new_element(
+        QualName::new(None, ns!(html), "span".into()),
+        None,
+    );
+    span.as_element()
+        .unwrap()
+        .attributes
+        .borrow_mut()
+        .insert("class", "section-number".to_string());
+    span.append(NodeRef::new_text(format!("{} ", number)));
+    span
+}
+
+fn detach_all(nodes: &[NodeRef]) {
+    for node in nodes {
+        node.detach();
+    }
+}
@@ -0,0 +1,235 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Html,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use minijinja::context;
+
+use crate::{
+    config::Config,
+    helper,
+    pagination::{page_path, paginate, render_pagination, PageQuery},
+    sorted_articles,
+    template::{self, ArticleContext, TermContext, Templates},
+    Article, ArticleStore,
+};
+
+// 文章的分类方式：标签或分类
+#[derive(Debug, Clone, Copy)]
+pub enum Taxonomy {
+    Tags,
+    Categories,
+}
+
+impl Taxonomy {
+    fn terms(self, article: &Article) -> &[String] {
+        match self {
+            Taxonomy::Tags => &article.tags,
+            Taxonomy::Categories => &article.categories,
+        }
+    }
+
+    pub fn base_path(self) -> &'static str {
+        match self {
+            Taxonomy::Tags => "/tags",
+            Taxonomy::Categories => "/categories",
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            Taxonomy::Tags => "Tags",
+            Taxonomy::Categories => "Categories",
+        }
+    }
+
+    // 模板中的 `taxonomy` 变量
+    fn context(self) -> minijinja::Value {
+        context! {
+            heading => self.heading(),
+            url => self.base_path(),
+        }
+    }
+
+    // 导航栏中对应的一项
+    fn section(self) -> &'static str {
+        self.base_path().trim_start_matches('/')
+    }
+}
+
+type PageResult = Result<Html<String>, (StatusCode, Html<String>)>;
+
+pub async fn tags_handler(
+    state: State<ArticleStore>,
+    templates: State<Templates>,
+) -> PageResult {
+    term_index_handler(Taxonomy::Tags, state, templates).await
+}
+
+pub async fn tag_handler(
+    Path(tag): Path<String>,
+    Query(query): Query<PageQuery>,
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+    templates: State<Templates>,
+) -> PageResult {
+    let page = query.page.unwrap_or(1);
+    term_listing_handler(Taxonomy::Tags, &tag, page, state, config, templates).await
+}
+
+pub async fn tag_page_handler(
+    Path((tag, page)): Path<(String, usize)>,
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+    templates: State<Templates>,
+) -> PageResult {
+    term_listing_handler(Taxonomy::Tags, &tag, page, state, config, templates).await
+}
+
+pub async fn categories_handler(
+    state: State<ArticleStore>,
+    templates: State<Templates>,
+) -> PageResult {
+    term_index_handler(Taxonomy::Categories, state, templates).await
+}
+
+pub async fn category_handler(
+    Path(category): Path<String>,
+    Query(query): Query<PageQuery>,
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+    templates: State<Templates>,
+) -> PageResult {
+    let page = query.page.unwrap_or(1);
+    term_listing_handler(Taxonomy::Categories, &category, page, state, config, templates).await
+}
+
+pub async fn category_page_handler(
+    Path((category, page)): Path<(String, usize)>,
+    state: State<ArticleStore>,
+    config: State<Arc<Config>>,
+    templates: State<Templates>,
+) -> PageResult {
+    term_listing_handler(Taxonomy::Categories, &category, page, state, config, templates).await
+}
+
+async fn term_index_handler(
+    taxonomy: Taxonomy,
+    state: State<ArticleStore>,
+    State(templates): State<Templates>,
+) -> PageResult {
+    let store = state.read().await;
+
+    Ok(Html(render_term_index(&store, taxonomy, &templates)))
+}
+
+async fn term_listing_handler(
+    taxonomy: Taxonomy,
+    term: &str,
+    page: usize,
+    state: State<ArticleStore>,
+    State(config): State<Arc<Config>>,
+    State(templates): State<Templates>,
+) -> PageResult {
+    let store = state.read().await;
+
+    render_term_listing(&store, taxonomy, term, page, &config, &templates)
+        .map(Html)
+        .ok_or_else(|| template::not_found_response(&templates))
+}
+
+// 标签与分类名称会成为页面地址中的一段，导出时是一个目录
+pub fn check_term(term: &str) -> Result<(), String> {
+    if term.trim().is_empty() || term.contains('/') || term == "." || term == ".." {
+        return Err(format!(
+            "`{}` must be a non-empty name without `/` and must not be `.` or `..`",
+            term
+        ));
+    }
+    Ok(())
+}
+
+pub fn term_path(taxonomy: Taxonomy, term: &str) -> String {
+    format!(
+        "{}/{}",
+        taxonomy.base_path(),
+        helper::encode_path_segment(term)
+    )
+}
+
+// 所有标签（或分类）及其文章数量，按名称排序；只统计可见的文章
+pub fn term_counts(store: &HashMap<String, Article>, taxonomy: Taxonomy) -> BTreeMap<&str, usize> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for article in store.values().filter(|article| article.is_visible()) {
+        for term in taxonomy.terms(article) {
+            *counts.entry(term.as_str()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// 列出所有标签（或分类）及其文章数量
+pub fn render_term_index(
+    store: &HashMap<String, Article>,
+    taxonomy: Taxonomy,
+    templates: &Templates,
+) -> String {
+    let terms: Vec<TermContext> = term_counts(store, taxonomy)
+        .into_iter()
+        .map(|(term, count)| TermContext::new(taxonomy, term, Some(count)))
+        .collect();
+
+    templates.render(
+        "terms.html",
+        context! {
+            section => taxonomy.section(),
+            taxonomy => taxonomy.context(),
+            terms,
+        },
+    )
+}
+
+// 列出某个标签（或分类）下的文章，没有文章或页码越界时返回 None
+pub fn render_term_listing(
+    store: &HashMap<String, Article>,
+    taxonomy: Taxonomy,
+    term: &str,
+    page: usize,
+    config: &Config,
+    templates: &Templates,
+) -> Option<String> {
+    let articles: Vec<(&String, &Article)> = sorted_articles(store)
+        .into_iter()
+        .filter(|(_, article)| taxonomy.terms(article).iter().any(|t| t == term))
+        .collect();
+
+    if articles.is_empty() {
+        return None;
+    }
+
+    let page = paginate(articles, page, config.page_size)?;
+
+    let base_path = term_path(taxonomy, term);
+    let articles: Vec<ArticleContext> = page
+        .items
+        .iter()
+        .map(|(id, article)| ArticleContext::new(id, article, config))
+        .collect();
+
+    Some(templates.render(
+        "tag.html",
+        context! {
+            section => taxonomy.section(),
+            taxonomy => taxonomy.context(),
+            term,
+            articles,
+            page => context! { current => page.current, total_pages => page.total_pages },
+            pagination => render_pagination(&page, |n| page_path(&base_path, n)),
+        },
+    ))
+}
@@ -0,0 +1,282 @@
+use axum::{http::StatusCode, response::Html};
+use minijinja::{path_loader, Environment, Value};
+use serde::Serialize;
+use std::{path::PathBuf, sync::Arc, time::SystemTime};
+
+use crate::{
+    config::{ColorScheme, Config},
+    directory::{self, directory_path},
+    helper,
+    publish_date::DateSource,
+    taxonomy::{term_path, Taxonomy},
+    theme::Theme,
+    Article,
+};
+
+// 页面模板，按站点的 template_dir、主题、上级主题的顺序查找。
+// 开发模式下每次渲染都重新读取，修改模板后刷新页面即可看到效果
+#[derive(Clone)]
+pub struct Templates {
+    environment: Arc<Environment<'static>>,
+    dirs: Vec<PathBuf>,
+    site: Value,
+    reload: bool,
+}
+
+impl Templates {
+    pub fn new(config: &Config, theme: &Theme) -> Self {
+        let site = Value::from_serialize(Site::new(config, theme));
+        Templates {
+            environment: Arc::new(environment(theme.template_dirs.clone(), site.clone())),
+            dirs: theme.template_dirs.clone(),
+            site,
+            reload: helper::is_development(),
+        }
+    }
+
+    // 渲染失败时返回错误信息，与 helper::read_file 的处理方式相同
+    pub fn render(&self, name: &str, context: Value) -> String {
+        let result = if self.reload {
+            environment(self.dirs.clone(), self.site.clone())
+                .get_template(name)
+                .and_then(|template| template.render(context))
+        } else {
+            self.environment
+                .get_template(name)
+                .and_then(|template| template.render(context))
+        };
+
+        result.unwrap_or_else(|e| render_error(name, e))
+    }
+
+    // 首页是站点自己的静态文件，同样按模板渲染，以便使用 `site` 变量（例如配色方案）
+    pub fn render_home(&self, source: &str) -> String {
+        let result = if self.reload {
+            environment(self.dirs.clone(), self.site.clone()).render_str(source, minijinja::context! {})
+        } else {
+            self.environment.render_str(source, minijinja::context! {})
+        };
+
+        result.unwrap_or_else(|e| render_error("index.html", e))
+    }
+
+    // 404 页面，处理函数与静态导出共用
+    pub fn render_not_found(&self) -> String {
+        self.render("404.html", minijinja::context! {})
+    }
+}
+
+fn environment(dirs: Vec<PathBuf>, site: Value) -> Environment<'static> {
+    let loaders: Vec<_> = dirs.into_iter().map(path_loader).collect();
+    let mut environment = Environment::new();
+    // 使用第一个包含该模板的目录
+    environment.set_loader(move |name| {
+        for loader in &loaders {
+            if let Some(source) = loader(name)? {
+                return Ok(Some(source));
+            }
+        }
+        Ok(None)
+    });
+    environment.add_global("site", site);
+    environment
+}
+
+fn render_error(name: &str, e: minijinja::Error) -> String {
+    eprintln!("Error rendering {}: {:#}", name, e);
+    format!("Error rendering template {}: {}", name, e)
+}
+
+pub fn not_found_response(templates: &Templates) -> (StatusCode, Html<String>) {
+    (StatusCode::NOT_FOUND, Html(templates.render_not_found()))
+}
+
+// 所有模板都能使用的 `site` 变量
+#[derive(Serialize)]
+struct Site<'a> {
+    title: &'a str,
+    description: Option<&'a str>,
+    author: Option<&'a str>,
+    language: &'a str,
+    base_url: Option<&'a str>,
+    theme: &'a str,
+    color_scheme: ColorScheme,
+    features: &'a crate::config::Features,
+    nav: Vec<NavItem>,
+    // 开发模式，模板据此显示只给作者看的提示
+    development: bool,
+}
+
+// 导航栏中的一项，`section` 与页面的 `section` 变量相同时标记为当前页
+#[derive(Serialize)]
+struct NavItem {
+    label: &'static str,
+    url: &'static str,
+    section: &'static str,
+}
+
+impl<'a> Site<'a> {
+    fn new(config: &'a Config, theme: &'a Theme) -> Self {
+        let mut nav = vec![NavItem {
+            label: "Articles",
+            url: "/articles",
+            section: "articles",
+        }];
+        if config.features.taxonomies {
+            nav.push(NavItem {
+                label: "Tags",
+                url: "/tags",
+                section: "tags",
+            });
+            nav.push(NavItem {
+                label: "Categories",
+                url: "/categories",
+                section: "categories",
+            });
+        }
+        if config.features.search {
+            nav.push(NavItem {
+                label: "Search",
+                url: "/search",
+                section: "search",
+            });
+        }
+
+        Site {
+            title: &config.title,
+            description: config.description.as_deref(),
+            author: config.author.as_deref(),
+            language: &config.language,
+            base_url: config.base_url.as_deref(),
+            theme: &theme.name,
+            color_scheme: config.color_scheme,
+            features: &config.features,
+            nav,
+            development: helper::is_development(),
+        }
+    }
+}
+
+// 中英文两种格式的日期
+#[derive(Serialize)]
+pub struct DateText {
+    english: String,
+    chinese: String,
+}
+
+impl DateText {
+    pub fn new(time: SystemTime, config: &Config) -> Self {
+        let (english, chinese) = helper::format_system_time(time, config.timezone);
+        DateText { english, chinese }
+    }
+}
+
+// 标签或分类
+#[derive(Serialize)]
+pub struct TermContext<'a> {
+    name: &'a str,
+    url: String,
+    count: Option<usize>,
+}
+
+impl<'a> TermContext<'a> {
+    pub fn new(taxonomy: Taxonomy, name: &'a str, count: Option<usize>) -> Self {
+        TermContext {
+            name,
+            url: term_path(taxonomy, name),
+            count,
+        }
+    }
+}
+
+// 面包屑导航中的一级目录
+#[derive(Serialize)]
+pub struct Breadcrumb {
+    name: String,
+    url: String,
+}
+
+impl Breadcrumb {
+    // 从文章列表到 `dir` 的各级目录，`dir` 为 None 时只有文章列表本身
+    pub fn ancestors(dir: Option<&str>) -> Vec<Breadcrumb> {
+        let mut breadcrumbs = vec![Breadcrumb {
+            name: "Articles".to_string(),
+            url: "/articles".to_string(),
+        }];
+
+        let Some(dir) = dir else {
+            return breadcrumbs;
+        };
+        let mut path = String::new();
+        for segment in dir.split('/') {
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(segment);
+            breadcrumbs.push(Breadcrumb {
+                name: segment.to_string(),
+                url: directory_path(&path),
+            });
+        }
+        breadcrumbs
+    }
+}
+
+// 模板中的 `article`
+#[derive(Serialize)]
+pub struct ArticleContext<'a> {
+    id: &'a str,
+    url: String,
+    // 渲染后的标题 HTML，以及用于 <title> 的纯文本
+    title: &'a str,
+    title_text: String,
+    description: &'a str,
+    author: Option<&'a str>,
+    content: &'a str,
+    date: DateText,
+    // 发布日期的来源：front_matter、file_name、git 或 modified
+    date_source: DateSource,
+    updated: Option<DateText>,
+    tags: Vec<TermContext<'a>>,
+    categories: Vec<TermContext<'a>>,
+    summary: Option<&'a str>,
+    // 子目录中的文章所在的各级目录，顶层文章为空
+    breadcrumbs: Vec<Breadcrumb>,
+    // 开发模式或预览时才能看到的文章
+    draft: bool,
+    scheduled: bool,
+}
+
+impl<'a> ArticleContext<'a> {
+    pub(crate) fn new(id: &'a str, article: &'a Article, config: &'a Config) -> Self {
+        ArticleContext {
+            id,
+            url: helper::article_path(id),
+            title: &article.title,
+            title_text: helper::html_to_text(&article.title),
+            description: &article.description,
+            author: config.author.as_deref(),
+            content: &article.content,
+            date: DateText::new(article.created_at, config),
+            date_source: article.date_source,
+            updated: article.updated.map(|updated| DateText::new(updated, config)),
+            tags: article
+                .tags
+                .iter()
+                .map(|tag| TermContext::new(Taxonomy::Tags, tag, None))
+                .collect(),
+            categories: article
+                .categories
+                .iter()
+                .map(|category| TermContext::new(Taxonomy::Categories, category, None))
+                .collect(),
+            summary: article.summary.as_deref(),
+            breadcrumbs: match directory::parent(id) {
+                Some(dir) => Breadcrumb::ancestors(Some(dir)),
+                None => Vec::new(),
+            },
+            draft: article.draft,
+            scheduled: !article.draft && !article.is_published(),
+        }
+    }
+}
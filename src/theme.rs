@@ -0,0 +1,130 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+// 主题的说明文件，可以省略
+const MANIFEST: &str = "theme.toml";
+
+// 主题是 theme_dir 下的一个目录：
+//
+// ```text
+// themes/default/
+//   theme.toml      可选
+//   templates/      页面模板
+//   static/         样式表与图片，以 /public 对外提供
+// ```
+//
+// 查找模板或静态文件时依次使用站点的 template_dir / static_dir、主题本身、
+// 以及 `extends` 指定的上级主题，因此只需放入要覆盖的那几个文件
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub template_dirs: Vec<PathBuf>,
+    pub static_dirs: Vec<PathBuf>,
+    pub highlight: HighlightThemes,
+}
+
+// ```toml
+// extends = "default"
+//
+// [highlight]
+// dark = "Solarized (dark)"
+// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Manifest {
+    extends: Option<String>,
+    highlight: HighlightSettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HighlightSettings {
+    light: Option<String>,
+    dark: Option<String>,
+}
+
+// 浅色与深色配色下使用的代码高亮主题（syntect 内置主题名）
+#[derive(Debug, Clone)]
+pub struct HighlightThemes {
+    pub light: String,
+    pub dark: String,
+}
+
+impl Default for HighlightThemes {
+    fn default() -> Self {
+        HighlightThemes {
+            light: "InspiredGitHub".to_string(),
+            dark: "base16-ocean.dark".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn load(config: &Config) -> anyhow::Result<Theme> {
+        let mut template_dirs = vec![config.template_dir.clone()];
+        let mut static_dirs = vec![config.static_dir.clone()];
+        let mut light = None;
+        let mut dark = None;
+
+        // 沿 `extends` 逐级向上，先出现的主题优先
+        let mut chain: Vec<String> = Vec::new();
+        let mut next = Some(config.theme.clone());
+        while let Some(name) = next {
+            if chain.contains(&name) {
+                anyhow::bail!("themes extend each other in a cycle: {} -> {}", chain.join(" -> "), name);
+            }
+
+            let dir = config.theme_dir.join(&name);
+            if !dir.is_dir() {
+                anyhow::bail!("theme `{}` not found in {}", name, config.theme_dir.display());
+            }
+
+            let manifest = read_manifest(&dir)?;
+            template_dirs.push(dir.join("templates"));
+            static_dirs.push(dir.join("static"));
+            light = light.or(manifest.highlight.light);
+            dark = dark.or(manifest.highlight.dark);
+
+            chain.push(name);
+            next = manifest.extends;
+        }
+
+        // 站点配置的高亮主题同时用于浅色与深色配色
+        if let Some(name) = &config.highlight_theme {
+            light = Some(name.clone());
+            dark = Some(name.clone());
+        }
+
+        let defaults = HighlightThemes::default();
+        Ok(Theme {
+            name: config.theme.clone(),
+            template_dirs,
+            static_dirs,
+            highlight: HighlightThemes {
+                light: light.unwrap_or(defaults.light),
+                dark: dark.unwrap_or(defaults.dark),
+            },
+        })
+    }
+
+    // 按优先级查找静态文件
+    pub fn find_static(&self, name: &str) -> Option<PathBuf> {
+        self.static_dirs
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+}
+
+fn read_manifest(dir: &Path) -> anyhow::Result<Manifest> {
+    let path = dir.join(MANIFEST);
+    if !path.is_file() {
+        return Ok(Manifest::default());
+    }
+
+    let source = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Error reading {}: {}", path.display(), e))?;
+    toml::from_str(&source).map_err(|e| anyhow::anyhow!("Error parsing {}: {}", path.display(), e))
+}
@@ -0,0 +1,220 @@
+use kuchiki::NodeRef;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, sync::LazyLock};
+
+use crate::{
+    bundle::{Bundle, BundleLinks},
+    config::Config,
+    footnote::Footnotes,
+    heading_anchor::HeadingAnchors,
+    math::Math,
+    mermaid::Mermaid,
+    responsive_image::ResponsiveImages,
+    table_of_contents::TableOfContents,
+    theme::Theme,
+};
+
+// 内置变换的注册表，按默认顺序排列
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::builtin);
+
+// 作用于整个 HTML 文档的一次处理
+pub trait Transform: Send + Sync {
+    // 在设置中引用该变换时使用的名称
+    fn name(&self) -> &'static str;
+
+    fn apply(&self, document: &NodeRef, context: &mut TransformContext) -> NodeRef;
+}
+
+// 变换执行时可用的配置，以及收集到的警告、生成的图片与检查过的文件
+pub struct TransformContext<'a> {
+    options: BTreeMap<String, serde_yaml::Value>,
+    pub warnings: Vec<String>,
+    // 正在渲染的文章包，普通文章为 None
+    pub bundle: Option<&'a Bundle<'a>>,
+    pub config: &'a Config,
+    pub theme: &'a Theme,
+    // 生成的图片文件名（位于 responsive_image::CACHE_DIR）
+    pub images: Vec<String>,
+    // 渲染时检查过的文件，变化后需要重新渲染
+    pub dependencies: Vec<PathBuf>,
+}
+
+impl<'a> TransformContext<'a> {
+    pub fn new(bundle: Option<&'a Bundle<'a>>, config: &'a Config, theme: &'a Theme) -> Self {
+        TransformContext {
+            options: BTreeMap::new(),
+            warnings: Vec::new(),
+            bundle,
+            config,
+            theme,
+            images: Vec::new(),
+            dependencies: Vec::new(),
+        }
+    }
+
+    // 读取当前变换的某项配置，缺失或类型不符时返回 None
+    pub fn option<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.options
+            .get(key)
+            .and_then(|value| serde_yaml::from_value(value.clone()).ok())
+    }
+
+    // 把当前变换的全部配置读取为结构体，配置有误时记录警告并使用默认值
+    pub fn options<T: DeserializeOwned + Default>(&mut self) -> T {
+        let mapping: serde_yaml::Mapping = self
+            .options
+            .iter()
+            .map(|(k, v)| (serde_yaml::Value::String(k.clone()), v.clone()))
+            .collect();
+
+        serde_yaml::from_value(serde_yaml::Value::Mapping(mapping)).unwrap_or_else(|e| {
+            self.warn(format!("invalid options: {}", e));
+            T::default()
+        })
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+}
+
+// 启用哪些变换、以什么顺序执行以及各自的配置。站点设置写在 blog.toml 的 `[transforms]` 中，
+// 文章可以在头部元数据中覆盖：
+//
+// ```yaml
+// transforms:
+//   order: [heading_anchors, table_of_contents, footnotes, math]
+//   disable: [mermaid]
+//   options:
+//     mermaid: { cli: mmdc }
+// ```
+//
+// 也可以直接写成列表，等同于只指定 `order`。使用 BTreeMap，序列化的结果可以用作渲染缓存的键
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TransformSettings {
+    pub order: Option<Vec<String>>,
+    pub disable: Vec<String>,
+    pub options: BTreeMap<String, BTreeMap<String, serde_yaml::Value>>,
+}
+
+impl TransformSettings {
+    // 头部元数据中的设置覆盖站点设置
+    fn merge(&self, article: &TransformSettings) -> TransformSettings {
+        let mut options = self.options.clone();
+        for (name, article_options) in &article.options {
+            options
+                .entry(name.clone())
+                .or_default()
+                .extend(article_options.clone());
+        }
+
+        TransformSettings {
+            order: article.order.clone().or_else(|| self.order.clone()),
+            disable: self
+                .disable
+                .iter()
+                .chain(&article.disable)
+                .cloned()
+                .collect(),
+            options,
+        }
+    }
+}
+
+// 允许 `transforms: [a, b]` 这种列表写法
+pub fn deserialize_settings<'de, D>(deserializer: D) -> Result<TransformSettings, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListOrSettings {
+        List(Vec<String>),
+        Settings(TransformSettings),
+    }
+
+    Ok(match Option::<ListOrSettings>::deserialize(deserializer)? {
+        Some(ListOrSettings::List(order)) => TransformSettings {
+            order: Some(order),
+            ..Default::default()
+        },
+        Some(ListOrSettings::Settings(settings)) => settings,
+        None => TransformSettings::default(),
+    })
+}
+
+// 按顺序登记的变换
+pub struct Registry {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Registry {
+    fn builtin() -> Self {
+        let mut registry = Registry {
+            transforms: Vec::new(),
+        };
+        registry.register(BundleLinks);
+        registry.register(ResponsiveImages);
+        registry.register(HeadingAnchors);
+        registry.register(TableOfContents);
+        registry.register(Footnotes);
+        registry.register(Mermaid);
+        registry.register(Math);
+        registry
+    }
+
+    fn register(&mut self, transform: impl Transform + 'static) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn Transform> {
+        self.transforms
+            .iter()
+            .find(|t| t.name() == name)
+            .map(|t| t.as_ref())
+    }
+
+    // 依次执行启用的变换，返回处理后的文档，警告等记录在 context 中
+    fn run(
+        &self,
+        document: NodeRef,
+        settings: &TransformSettings,
+        context: &mut TransformContext,
+    ) -> NodeRef {
+        let order: Vec<&str> = match &settings.order {
+            Some(order) => order.iter().map(String::as_str).collect(),
+            None => self.transforms.iter().map(|t| t.name()).collect(),
+        };
+
+        let mut document = document;
+        for name in order {
+            if settings.disable.iter().any(|d| d == name) {
+                continue;
+            }
+
+            let Some(transform) = self.get(name) else {
+                context.warn(format!("unknown transform `{}`", name));
+                continue;
+            };
+
+            context.options = settings.options.get(name).cloned().unwrap_or_default();
+            let start = context.warnings.len();
+            document = transform.apply(&document, context);
+            for warning in &mut context.warnings[start..] {
+                *warning = format!("{}: {}", name, warning);
+            }
+        }
+
+        document
+    }
+}
+
+// 使用站点设置与文章设置运行变换流水线
+pub fn run_pipeline(
+    document: NodeRef,
+    article: &TransformSettings,
+    context: &mut TransformContext,
+) -> NodeRef {
+    REGISTRY.run(document, &context.config.transforms.merge(article), context)
+}
@@ -0,0 +1,225 @@
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecursiveMode, Watcher,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    apply_article, article_id, bundle, config::Config, live_reload::LiveReload, process_article,
+    redirect::{self, RedirectMap},
+    resolve_id, search::SearchIndex, sync_articles_with_filesystem, theme::Theme, ArticleStore,
+};
+
+// 同一次保存往往触发多个事件，收集一小段时间内的事件后再统一处理
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+// 启动后台任务，监听 articles 目录及其子目录的创建、修改、删除与重命名，并更新文章存储与搜索索引
+pub fn spawn(
+    store: ArticleStore,
+    index: SearchIndex,
+    redirects: RedirectMap,
+    live_reload: LiveReload,
+    config: Arc<Config>,
+    theme: Arc<Theme>,
+) -> anyhow::Result<()> {
+    let articles_dir = config.content_dir.clone();
+    if !articles_dir.is_dir() {
+        eprintln!(
+            "{} does not exist, not watching for changes",
+            articles_dir.display()
+        );
+        return Ok(());
+    }
+
+    // 事件中的路径是绝对路径，换成与文章存储一致的 content_dir 下的路径
+    let canonical_dir = articles_dir.canonicalize()?;
+    let local = move |path: PathBuf| match path.strip_prefix(&canonical_dir) {
+        Ok(relative) => articles_dir.join(relative),
+        Err(_) => path,
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let _ = tx.send(event);
+    })?;
+    watcher.watch(&config.content_dir, RecursiveMode::Recursive)?;
+
+    tokio::spawn(async move {
+        // watcher 被释放后便不再产生事件，因此让它与任务一同存活
+        let _watcher = watcher;
+
+        while let Some(event) = rx.recv().await {
+            let mut paths = HashSet::new();
+            let mut renames = Vec::new();
+            let mut rescan = collect(event, &mut paths, &mut renames);
+
+            while let Ok(Some(event)) = tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                rescan |= collect(event, &mut paths, &mut renames);
+            }
+
+            let paths: HashSet<PathBuf> = paths.into_iter().map(&local).collect();
+            let renames: Vec<(PathBuf, PathBuf)> = renames
+                .into_iter()
+                .map(|(from, to)| (local(from), local(to)))
+                .collect();
+
+            // 处理之前各文件对应的 id，用于发现改名
+            let before = file_ids(&store).await;
+
+            // 移动或删除整个目录时只会收到目录本身的事件，同样重新扫描
+            rescan |= paths.iter().any(|path| is_directory_change(path));
+
+            if rescan {
+                // 事件丢失时无法得知具体变化，重新扫描整个目录
+                sync_articles_with_filesystem(&store, &index, &config, &theme).await;
+                for id in store.read().await.keys() {
+                    live_reload.notify(id);
+                }
+            } else {
+                // 先处理已删除的文件：设置了 slug 的文章改名后 id 不变，需要先移除旧记录
+                let mut paths: Vec<PathBuf> = paths.into_iter().collect();
+                paths.sort_by_key(|path| path.exists());
+                for path in paths {
+                    for id in apply_change(&store, &index, &path, &config, &theme).await {
+                        live_reload.notify(&id);
+                    }
+                }
+            }
+
+            // 别名随文章一同更新，重定向层不必在每次请求时遍历文章
+            let aliases = redirect::aliases(&*store.read().await);
+            redirects.write().await.set_aliases(aliases);
+            record_moves(&store, &redirects, before, &renames).await;
+        }
+    });
+
+    Ok(())
+}
+
+// 记录事件涉及的文件与重命名（旧路径、新路径），返回是否需要全量扫描
+fn collect(
+    event: notify::Result<Event>,
+    paths: &mut HashSet<PathBuf>,
+    renames: &mut Vec<(PathBuf, PathBuf)>,
+) -> bool {
+    match event {
+        // 读取文件本身也会产生访问事件，忽略它们以免重复渲染
+        Ok(event) if matches!(event.kind, EventKind::Access(_)) => false,
+        Ok(event) => {
+            if let (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) =
+                (event.kind, event.paths.as_slice())
+            {
+                renames.push((from.clone(), to.clone()));
+            }
+            paths.extend(event.paths.iter().cloned());
+            event.need_rescan()
+        }
+        Err(e) => {
+            eprintln!("Error watching articles: {}", e);
+            true
+        }
+    }
+}
+
+// 事件涉及的是目录而不是文件：目录仍然存在，或已被移除且没有扩展名
+fn is_directory_change(path: &Path) -> bool {
+    path.is_dir() || (!path.exists() && path.extension().is_none())
+}
+
+// 根据文件当前的状态更新存储：文件存在则重新渲染，否则移除。返回发生变化的文章 id。
+// 文章包中的其他文件变化时重新渲染所属的文章，更新缺失文件的警告并刷新页面
+async fn apply_change(
+    store: &ArticleStore,
+    index: &SearchIndex,
+    path: &Path,
+    config: &Arc<Config>,
+    theme: &Arc<Theme>,
+) -> Vec<String> {
+    let (path, path_id) = match article_id(&config.content_dir, path) {
+        Some(path_id) => (path.to_path_buf(), path_id),
+        None => {
+            let Some(index_file) = bundle::index_file(&config.content_dir, path) else {
+                return Vec::new();
+            };
+            let Some(path_id) = article_id(&config.content_dir, &index_file) else {
+                return Vec::new();
+            };
+            (index_file, path_id)
+        }
+    };
+    let path = path.as_path();
+
+    if path.is_file() {
+        // 渲染时不持有锁，避免阻塞读取
+        match process_article(path, config, theme).await {
+            Ok(article) => {
+                let id = resolve_id(&path_id, article.slug.as_deref());
+                let mut store = store.write().await;
+                apply_article(&mut store, &mut *index.write().await, id, article)
+            }
+            Err(e) => {
+                eprintln!("Error processing {}: {}", path.display(), e);
+                Vec::new()
+            }
+        }
+    } else {
+        let mut store = store.write().await;
+        let removed: Vec<String> = store
+            .iter()
+            .filter(|(_, article)| article.file_path == path)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let mut index = index.write().await;
+        for id in &removed {
+            store.remove(id);
+            index.remove(id);
+        }
+        removed
+    }
+}
+
+async fn file_ids(store: &ArticleStore) -> HashMap<PathBuf, String> {
+    store
+        .read()
+        .await
+        .iter()
+        .map(|(id, article)| (article.file_path.clone(), id.clone()))
+        .collect()
+}
+
+// 文件改名、移动（包括所在目录改名）或修改 slug 后，记录旧地址到新地址的重定向
+async fn record_moves(
+    store: &ArticleStore,
+    redirects: &RedirectMap,
+    before: HashMap<PathBuf, String>,
+    renames: &[(PathBuf, PathBuf)],
+) {
+    let after = file_ids(store).await;
+
+    let mut moves = Vec::new();
+    for (path, old_id) in before {
+        let new_path = renames
+            .iter()
+            .find_map(|(from, to)| Some(to.join(path.strip_prefix(from).ok()?)))
+            .unwrap_or(path);
+        match after.get(&new_path) {
+            Some(new_id) if *new_id != old_id => moves.push((old_id, new_id.clone())),
+            _ => {}
+        }
+    }
+
+    if moves.is_empty() {
+        return;
+    }
+    let mut redirects = redirects.write().await;
+    for (old_id, new_id) in moves {
+        println!("Redirecting /articles/{} to /articles/{}", old_id, new_id);
+        redirects.insert(&format!("/articles/{}", old_id), &format!("/articles/{}", new_id));
+    }
+}